@@ -0,0 +1,84 @@
+//! Times a full run of `para` over a generated project with thousands of
+//! emitted files, once on a single thread and once on every core.
+//!
+//! ```sh
+//! cargo bench --bench pipeline
+//! ```
+
+use std::{
+    fs,
+    path::Path,
+    process::Command,
+    time::{Duration, Instant},
+};
+
+const DIRS: usize = 50;
+const FILES_PER_DIR: usize = 100;
+const RUNS: usize = 3;
+
+fn main() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let root = dir.path();
+    fs::write(
+        root.join("tsconfig.json"),
+        r#"{ "compilerOptions": { "baseUrl": ".", "paths": { "@/*": ["src/*"] }, "outDir": "dist" } }"#,
+    )
+    .unwrap();
+    for i in 0..DIRS {
+        fs::create_dir_all(root.join(format!("src/m{i}"))).unwrap();
+        for j in 0..FILES_PER_DIR {
+            fs::write(root.join(format!("src/m{i}/f{j}.ts")), "").unwrap();
+        }
+    }
+
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let single = measure(root, 1);
+    let parallel = measure(root, cores);
+    println!(
+        "{} files: {:?} with 1 job, {:?} with {} jobs ({:.2}x)",
+        DIRS * FILES_PER_DIR,
+        single,
+        parallel,
+        cores,
+        single.as_secs_f64() / parallel.as_secs_f64()
+    );
+}
+
+/// The fastest of a few uncached runs over a freshly emitted `dist`.
+fn measure(root: &Path, jobs: usize) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            emit(root);
+            let start = Instant::now();
+            let status = Command::new(env!("CARGO_BIN_EXE_para"))
+                .current_dir(root)
+                .args(["--no-cache", "--log", "error", "--jobs", &jobs.to_string()])
+                .status()
+                .unwrap();
+            let elapsed = start.elapsed();
+            assert!(status.success());
+            let rewritten = fs::read_to_string(root.join("dist/m0/f0.js")).unwrap();
+            assert!(rewritten.starts_with("import { f0 as f1 } from \"../m1/f0\";"));
+            elapsed
+        })
+        .min()
+        .unwrap()
+}
+
+/// Writes what `tsc` would emit: every file imports a few others by alias.
+fn emit(root: &Path) {
+    let dist = root.join("dist");
+    let _ = fs::remove_dir_all(&dist);
+    for i in 0..DIRS {
+        fs::create_dir_all(dist.join(format!("m{i}"))).unwrap();
+        for j in 0..FILES_PER_DIR {
+            let mut source = String::new();
+            for k in 1..=4 {
+                let (dir, file) = ((i + k) % DIRS, (j * k) % FILES_PER_DIR);
+                source += &format!("import {{ f{file} as f{k} }} from \"@/m{dir}/f{file}\";\n");
+            }
+            source += &format!("export const f{j} = () => [f1, f2, f3, f4];\n").repeat(50);
+            fs::write(dist.join(format!("m{i}/f{j}.js")), source).unwrap();
+        }
+    }
+}
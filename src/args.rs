@@ -12,7 +12,7 @@ pub fn parse_cli_paths(
     if paths.is_empty() {
         paths.push(Utf8PathBuf::from("./tsconfig.json"));
     }
-    crate::log::debug::tsconfig_paths(&paths, &logger);
+    crate::log::debug::tsconfig_paths(&paths, logger);
     paths
 }
 
@@ -25,10 +25,6 @@ pub fn parse_cli_exclude_paths(exclude: Vec<String>, merge_with_default: bool) -
                 "node_modules".to_string(),
                 ".git".to_string(),
                 ".gitignore".to_string(),
-                "*.ts".to_string(),
-                "*.tsx".to_string(),
-                "*.cts".to_string(),
-                "*.mts".to_string(),
                 "*.json".to_string(),
                 "*.lock".to_string(),
                 "*.toml".to_string(),
@@ -44,34 +40,75 @@ pub fn parse_cli_exclude_paths(exclude: Vec<String>, merge_with_default: bool) -
 pub fn handle_cli_cache_command(
     cache_action: Option<crate::cli::CacheCommand>,
     cache_dir: &PathBuf,
+    max_age: Option<u64>,
     logger: &crate::log::Logger,
 ) -> Option<()> {
     use crate::cli::CacheCommand::*;
     match cache_action {
         None => return None,
         Some(Clear) => {
-            let cache = super::cache::parse_cache(cache_dir)?;
-            if cache.is_empty() {
-                logger.info("Cache is already empty.");
-            } else {
-                std::fs::remove_file(cache_dir).ok()?;
-                logger.info("Cache cleared.");
-            }
-        }
-        Some(Dump) => {
-            let cache = super::cache::parse_cache(cache_dir)?;
-            if cache.is_empty() {
-                logger.info("Cache empty.");
-            } else {
-                logger.info(cache);
+            let _lock = super::cache::lock_cache(cache_dir, super::cache::LOCK_TIMEOUT, logger);
+            match super::cache::parse_cache(cache_dir) {
+                Some(cache) if cache.is_current() && cache.is_empty() => {
+                    logger.info("Cache is already empty.")
+                }
+                // stale and corrupt caches are cleared too
+                _ => {
+                    super::cache::remove_cache(cache_dir).ok();
+                    logger.info("Cache cleared.");
+                }
             }
         }
+        Some(Dump) => match super::cache::parse_cache(cache_dir) {
+            Some(cache) if cache.is_empty() => logger.info("Cache empty."),
+            Some(cache) => logger.info(cache),
+            None => logger.warn("Cache is corrupt."),
+        },
         Some(Path) => {
-            logger.info(&format!(
+            logger.info(format!(
                 "Cache directory: {}",
                 cache_dir.display().underline()
             ));
         }
+        Some(Prune) => {
+            let max_age = max_age.map(|days| std::time::Duration::from_secs(days * 24 * 60 * 60));
+            let pruned = super::cache::prune_caches(cache_dir, max_age, logger);
+            logger.info(format!(
+                "Pruned {} entries and {} caches.",
+                pruned.entries, pruned.caches
+            ));
+        }
+        Some(Stats) => match super::cache::parse_cache(cache_dir) {
+            Some(cache) => {
+                let size = std::fs::metadata(cache_dir).map_or(0, |m| m.len());
+                let hit_ratio = match cache.last_run().and_then(|run| run.hit_ratio()) {
+                    Some(ratio) => format!("{:.1}%", ratio * 100.0),
+                    None => "n/a".to_string(),
+                };
+                logger.info(format!(
+                    "{} entries, {} bytes on disk, last run hit ratio {}",
+                    cache.len(),
+                    size,
+                    hit_ratio
+                ));
+            }
+            None => logger.warn("Cache is corrupt."),
+        },
+        Some(List) => {
+            for file in super::cache::list_caches(cache_dir) {
+                let project = match &file.cache {
+                    Some(cache) => cache.cwd().unwrap_or("<unknown project>").to_string(),
+                    None => "<corrupt>".to_string(),
+                };
+                logger.info(format!(
+                    "{}\u{9}{} entries\u{9}{} bytes\u{9}{}",
+                    file.path.display().underline(),
+                    file.cache.as_ref().map_or(0, |cache| cache.len()),
+                    file.size,
+                    project
+                ));
+            }
+        }
     };
     Some(())
 }
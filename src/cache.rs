@@ -1,111 +1,461 @@
 use crate::{
-    log::{verbose, Logger},
-    utils::{create_all_dir_until_file, FileHash},
+    log::{error, verbose, warn, Logger},
+    utils::{create_all_dir_until_file, relative_path, write_atomic, FileHash},
 };
 use clean_path::Clean;
 use serde::{Deserialize, Serialize};
 use sha2::Digest;
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fs::{File, TryLockError},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Version of the cache file format. Bump it whenever the meaning of an entry
+/// changes, so that caches written by older versions of para are discarded.
+pub const CACHE_VERSION: u32 = 2;
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct Cache {
+    /// Caches written before versioning have no version and read as `0`.
+    #[serde(default)]
+    version: u32,
+    /// The project root (the working directory of the run) the cache belongs to.
+    #[serde(default)]
+    cwd: Option<String>,
+    #[serde(default)]
+    last_run: Option<RunStats>,
     cache: Vec<Entry>,
 }
 
+/// Cache hits and misses of a run.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RunStats {
+    pub hits: usize,
+    pub misses: usize,
+}
+
+impl RunStats {
+    /// The share of files that were hits, or `None` if no file was looked up.
+    pub fn hit_ratio(&self) -> Option<f64> {
+        match self.hits + self.misses {
+            0 => None,
+            total => Some(self.hits as f64 / total as f64),
+        }
+    }
+}
+
+/// Just the version of a cache file, readable whatever the format.
+#[derive(Deserialize)]
+struct Header {
+    #[serde(default)]
+    version: u32,
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 struct Entry {
-    hash: String,
     path: String,
+    hash: String,
+    config: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stamp: Option<FileStamp>,
+}
+
+/// What para remembers about a file it has processed: the hash of its
+/// contents and the fingerprint of the config it was resolved with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedFile {
+    pub hash: String,
+    pub config: String,
+    /// The metadata of the file when it was hashed.
+    pub stamp: Option<FileStamp>,
 }
 
+/// Size, modification time and inode of a file. When all three are unchanged
+/// since the last run, the file is assumed unchanged without hashing it.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FileStamp {
+    pub size: u64,
+    /// Nanoseconds since the unix epoch.
+    pub mtime: i64,
+    /// Always `0` on platforms without inodes.
+    pub inode: u64,
+}
+
+impl FileStamp {
+    pub fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        let mtime = metadata
+            .modified()
+            .ok()?
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .ok()?;
+        #[cfg(unix)]
+        let inode = std::os::unix::fs::MetadataExt::ino(&metadata);
+        #[cfg(not(unix))]
+        let inode = 0;
+        Some(Self {
+            size: metadata.len(),
+            mtime: i64::try_from(mtime.as_nanos()).ok()?,
+            inode,
+        })
+    }
+}
+
+/// The in-memory cache, keyed by the path of each processed file.
+pub type CacheMap = HashMap<PathBuf, CachedFile>;
+
 impl Cache {
+    fn new(version: u32, cache: Vec<Entry>) -> Self {
+        Self {
+            version,
+            cwd: None,
+            last_run: None,
+            cache,
+        }
+    }
+    /// Whether the cache was written in the current format.
+    pub fn is_current(&self) -> bool {
+        self.version == CACHE_VERSION
+    }
     pub fn is_empty(&self) -> bool {
         self.cache.is_empty()
     }
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+    pub fn cwd(&self) -> Option<&str> {
+        self.cwd.as_deref()
+    }
+    pub fn last_run(&self) -> Option<RunStats> {
+        self.last_run
+    }
 }
 
 impl std::fmt::Display for Cache {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "Cache:")?;
+        writeln!(f, "Cache (format v{}):", self.version)?;
+        if let Some(cwd) = &self.cwd {
+            writeln!(f, "Project: {}", cwd)?;
+        }
         for bucket in &self.cache {
-            writeln!(f, "{}\u{9}{}", bucket.hash.to_uppercase(), bucket.path)?;
+            writeln!(
+                f,
+                "{}\u{9}{}\u{9}{}",
+                bucket.hash.to_uppercase(),
+                &bucket.config[..bucket.config.len().min(8)],
+                bucket.path
+            )?;
         }
         Ok(())
     }
 }
 
-impl From<Vec<Entry>> for Cache {
-    fn from(cache: Vec<Entry>) -> Self {
-        Self { cache }
-    }
-}
-
-impl From<Cache> for HashMap<String, PathBuf> {
+impl From<Cache> for CacheMap {
     fn from(cache: Cache) -> Self {
-        let mut cache_hashmap: HashMap<String, PathBuf> = HashMap::new();
-        for bucket in cache.cache {
-            cache_hashmap.insert(bucket.hash, PathBuf::from(bucket.path));
-        }
-        cache_hashmap
+        cache
+            .cache
+            .into_iter()
+            .map(|bucket| {
+                (
+                    PathBuf::from(bucket.path),
+                    CachedFile {
+                        hash: bucket.hash,
+                        config: bucket.config,
+                        stamp: bucket.stamp,
+                    },
+                )
+            })
+            .collect()
     }
 }
 
-impl From<HashMap<String, PathBuf>> for Cache {
-    fn from(cache: HashMap<String, PathBuf>) -> Self {
-        let mut cache_vec: Vec<Entry> = vec![];
-        for (hash, path) in cache {
-            cache_vec.push(Entry {
-                hash,
-                path: path.to_str().unwrap().to_string(),
-            });
-        }
-        Self { cache: cache_vec }
+impl From<CacheMap> for Cache {
+    fn from(cache: CacheMap) -> Self {
+        let mut cache_vec: Vec<Entry> = cache
+            .into_iter()
+            // TOML cannot hold paths that are not UTF-8, so those are never cached
+            .filter_map(|(path, file)| {
+                Some(Entry {
+                    path: path.to_str()?.to_string(),
+                    hash: file.hash,
+                    config: file.config,
+                    stamp: file.stamp,
+                })
+            })
+            .collect();
+        cache_vec.sort_by(|a, b| a.path.cmp(&b.path));
+        Self::new(CACHE_VERSION, cache_vec)
     }
 }
 
-pub fn parse_cache(cache_dir: &PathBuf) -> Option<Cache> {
+pub fn parse_cache(cache_dir: &Path) -> Option<Cache> {
     let toml_string = std::fs::read_to_string(cache_dir).ok()?;
     if toml_string.is_empty() {
-        return Some(Cache { cache: vec![] });
+        return Some(Cache::new(CACHE_VERSION, vec![]));
+    }
+    // Entries of other versions may not even parse, so they are dropped here
+    // and the caller decides what to do with a stale cache.
+    let Header { version } = toml::from_str(&toml_string).ok()?;
+    if version != CACHE_VERSION {
+        return Some(Cache::new(version, vec![]));
     }
     toml::from_str(&toml_string).ok()?
 }
 
-pub fn load_cache(
-    cache_dir: &PathBuf,
-    logger: &Logger,
-) -> anyhow::Result<HashMap<String, PathBuf>> {
-    if let Some(cache) = parse_cache(cache_dir) {
-        return Ok(cache.into());
+/// Loads the cache of the project at `root`, with every path made absolute.
+pub fn load_cache(cache_dir: &Path, root: &Path, logger: &Logger) -> CacheMap {
+    match parse_cache(cache_dir) {
+        Some(cache) if cache.version == CACHE_VERSION => {
+            return rebase_paths(cache.into(), |path| root.join(path))
+        }
+        Some(_) => {
+            logger.info("Cache was written by another version of para so we're starting fresh.");
+            return HashMap::new();
+        }
+        None => (),
     }
     logger.warn("Cache was corrupt so we're starting fresh.");
-    Ok(HashMap::new())
+    HashMap::new()
 }
 
-pub fn save_cache(cache_dir: &PathBuf, cache: Cache) -> anyhow::Result<()> {
+/// Maps every path of a cache. Paths are stored relative to the project root,
+/// so that a cache stays valid when the project is checked out elsewhere.
+fn rebase_paths(cache: CacheMap, rebase: impl Fn(&Path) -> PathBuf) -> CacheMap {
+    cache
+        .into_iter()
+        .map(|(path, file)| (rebase(&path), file))
+        .collect()
+}
+
+pub fn save_cache(cache_dir: &Path, cache: Cache) -> anyhow::Result<()> {
     let toml_string = toml::to_string(&cache)?;
-    std::fs::write(cache_dir, toml_string)?;
+    write_atomic(cache_dir, toml_string.as_bytes())?;
     Ok(())
 }
 
+/// Saves the entries this run added or changed on top of what the cache file
+/// holds now, instead of overwriting it, so that entries saved by para runs
+/// that finished in the meantime are kept.
+pub fn merge_cache(
+    cache_dir: &Path,
+    root: &Path,
+    loaded: &CacheMap,
+    cache: CacheMap,
+    last_run: RunStats,
+    logger: &Logger,
+) -> anyhow::Result<()> {
+    let _lock = lock_cache(cache_dir, LOCK_TIMEOUT, logger);
+    let mut merged: CacheMap = match parse_cache(cache_dir) {
+        Some(current) if current.version == CACHE_VERSION => {
+            rebase_paths(current.into(), |path| root.join(path))
+        }
+        _ => CacheMap::new(),
+    };
+    merged.extend(
+        cache
+            .into_iter()
+            .filter(|(path, file)| loaded.get(path) != Some(file)),
+    );
+    // paths outside of the project stay absolute
+    let mut merged = Cache::from(rebase_paths(merged, |path| {
+        path.strip_prefix(root).unwrap_or(path).to_path_buf()
+    }));
+    merged.cwd = Some(root.to_string_lossy().into_owned());
+    merged.last_run = Some(last_run);
+    save_cache(cache_dir, merged)
+}
+
+/// A cache file found in a cache directory.
+pub struct CacheFile {
+    pub path: PathBuf,
+    /// `None` if the file is corrupt.
+    pub cache: Option<Cache>,
+    pub size: u64,
+}
+
+/// Lists every cache file in the directory holding `cache_dir`, i.e. the
+/// caches of every project para has run in.
+pub fn list_caches(cache_dir: &Path) -> Vec<CacheFile> {
+    let Some(Ok(entries)) = cache_dir.parent().map(std::fs::read_dir) else {
+        return vec![];
+    };
+    let mut caches = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "para"))
+        .map(|path| CacheFile {
+            cache: parse_cache(&path),
+            size: std::fs::metadata(&path).map_or(0, |m| m.len()),
+            path,
+        })
+        .collect::<Vec<_>>();
+    caches.sort_by(|a, b| a.path.cmp(&b.path));
+    caches
+}
+
+/// Whether a file looks like a cache para wrote, in any format version, even
+/// if it can no longer be read as one. para creates empty cache files too.
+fn written_by_para(path: &Path) -> bool {
+    match std::fs::read_to_string(path) {
+        Ok(contents) if contents.is_empty() => true,
+        Ok(contents) => toml::from_str::<toml::Table>(&contents)
+            .is_ok_and(|table| table.contains_key("cache") || table.contains_key("version")),
+        Err(_) => false,
+    }
+}
+
+/// Deletes a cache file. Take its lock first; the lock file itself is kept,
+/// as another run may be waiting on it.
+pub fn remove_cache(cache_dir: &Path) -> std::io::Result<()> {
+    std::fs::remove_file(cache_dir)
+}
+
+/// What `prune_caches` removed.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Pruned {
+    pub entries: usize,
+    pub caches: usize,
+}
+
+/// Removes the entries of every cache whose files no longer exist, and deletes
+/// the caches of projects that no longer exist, that are corrupt or stale, or
+/// (with `max_age`) that were not written to for that long. Files that para
+/// did not write are left alone, whatever their name.
+pub fn prune_caches(cache_dir: &Path, max_age: Option<Duration>, logger: &Logger) -> Pruned {
+    let mut pruned = Pruned::default();
+    for file in list_caches(cache_dir) {
+        if !written_by_para(&file.path) {
+            continue;
+        }
+        let _lock = lock_cache(&file.path, LOCK_TIMEOUT, logger);
+        // another run may have merged into the cache since it was listed
+        let modified = std::fs::metadata(&file.path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        let expired = max_age.is_some_and(|max_age| {
+            modified
+                .and_then(|modified| modified.elapsed().ok())
+                .is_some_and(|age| age >= max_age)
+        });
+        let delete = |pruned: &mut Pruned| {
+            if remove_cache(&file.path).is_ok() {
+                pruned.caches += 1;
+            }
+        };
+        let Some(mut cache) =
+            parse_cache(&file.path).filter(|cache| cache.version == CACHE_VERSION && !expired)
+        else {
+            delete(&mut pruned);
+            continue;
+        };
+        // without a recorded project there is nothing to check the entries against
+        let Some(root) = cache.cwd.clone().map(PathBuf::from) else {
+            continue;
+        };
+        if !root.is_dir() {
+            delete(&mut pruned);
+            continue;
+        }
+        let before = cache.len();
+        cache.cache.retain(|entry| root.join(&entry.path).exists());
+        let removed = before - cache.len();
+        if removed > 0 && save_cache(&file.path, cache).is_ok() {
+            pruned.entries += removed;
+        }
+    }
+    pruned
+}
+
+/// How long to wait for another para process to release the cache.
+pub const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An advisory lock on a cache file, released when dropped.
+pub struct CacheLock(#[allow(dead_code)] File);
+
+/// Takes the advisory lock of a cache file, waiting up to `timeout` for other
+/// para processes to release it. The lock lives in a sibling `.lock` file, as
+/// the cache file itself is replaced on every save.
+///
+/// Returns `None` (after warning) when the lock cannot be taken, in which case
+/// para carries on without it rather than failing the build.
+pub fn lock_cache(cache_dir: &Path, timeout: Duration, logger: &Logger) -> Option<CacheLock> {
+    let lock_path = lock_path(cache_dir);
+    let start = Instant::now();
+    let file = File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .inspect_err(|_| warn::cache_locked(&lock_path, logger))
+        .ok()?;
+    loop {
+        match file.try_lock() {
+            Ok(()) => return Some(CacheLock(file)),
+            Err(TryLockError::WouldBlock) if start.elapsed() < timeout => {
+                std::thread::sleep(Duration::from_millis(50))
+            }
+            Err(_) => {
+                warn::cache_locked(&lock_path, logger);
+                return None;
+            }
+        }
+    }
+}
+
+fn lock_path(cache_dir: &Path) -> PathBuf {
+    cache_dir.with_extension("para.lock")
+}
+
 /// Creates a para directory where your OS likes to store cache files. If the
 /// directory already exists, it will return the path to the existing directory.
-pub fn generate_cache_dir(logger: &Logger) -> Result<PathBuf, std::io::Error> {
+///
+/// A `custom` path (from `--cache-dir` or `PARA_CACHE_DIR`) replaces it: a
+/// `*.para` path is the cache file itself, anything else is a directory with
+/// a cache file for each project. Those are named after the path from the
+/// directory to the project `root`, which stays the same across checkouts as
+/// long as both move together (e.g. with a cache in `node_modules/.cache`).
+///
+/// A cache file that cannot be created is logged and returned as `Err`.
+pub fn generate_cache_dir(
+    custom: Option<&Path>,
+    root: &Path,
+    logger: &Logger,
+) -> Result<PathBuf, PathBuf> {
+    if let Some(custom) = custom {
+        let custom = root.join(custom).clean();
+        let project_cache_path = match custom.extension().is_some_and(|ext| ext == "para") {
+            true => custom,
+            false => {
+                let project = match (camino::Utf8Path::from_path(&custom), root.to_str()) {
+                    (Some(dir), Some(root)) => relative_path(dir, root.into()).to_string(),
+                    _ => root.to_string_lossy().into_owned(),
+                };
+                let project_hash: FileHash = sha2::Sha256::digest(project.as_bytes());
+                custom.join(format!("{:x}.para", project_hash))
+            }
+        };
+        let dir = project_cache_path.parent().unwrap_or(Path::new("."));
+        verbose::cache_dir(&project_cache_path, logger);
+        return match create_all_dir_until_file(dir, &project_cache_path) {
+            Ok(_) => Ok(project_cache_path),
+            Err(e) => {
+                error::os_error(&project_cache_path, &e.into());
+                Err(project_cache_path)
+            }
+        };
+    }
+
     // locate the root cache directory for para
-    let dirs = directories::ProjectDirs::from("com", "cuppachino", "para")
-        .ok_or_else(|| {
-            std::io::Error::new(std::io::ErrorKind::NotFound, "Failed to get project dirs")
-        })
-        .unwrap();
+    let Some(dirs) = directories::ProjectDirs::from("com", "cuppachino", "para") else {
+        let e = anyhow::anyhow!("Failed to find the user cache directory");
+        error::os_error(root, &e);
+        return Err(root.to_path_buf());
+    };
 
     // hash the current working directory's path to get a unique hash for a blank file in the cache directory.
-    let cwd_hash: FileHash = sha2::Sha256::digest(
-        std::env::current_dir()
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .as_bytes(),
-    );
+    let cwd_hash: FileHash = sha2::Sha256::digest(root.to_string_lossy().as_bytes());
 
     // assemble the complete path to the cache file for [this] project
     let project_cache_path = dirs
@@ -117,8 +467,231 @@ pub fn generate_cache_dir(logger: &Logger) -> Result<PathBuf, std::io::Error> {
     verbose::cache_dir(&dirs.cache_dir().to_path_buf(), logger);
     verbose::cache_dir(&project_cache_path, logger);
 
-    create_all_dir_until_file(&dirs.cache_dir(), &project_cache_path)
-        .expect("Failed to create/open cache file. Check user permissions.");
+    match create_all_dir_until_file(dirs.cache_dir(), &project_cache_path) {
+        Ok(_) => Ok(project_cache_path),
+        Err(e) => {
+            error::os_error(&project_cache_path, &e.into());
+            Err(project_cache_path)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Level;
+
+    #[test]
+    fn discards_caches_from_other_format_versions() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("cache.para");
+        let logger = Logger(Level::Error);
+
+        std::fs::write(&path, "[[cache]]\nhash = \"abc\"\npath = \"a.js\"\n").unwrap();
+        assert_eq!(parse_cache(&path).unwrap().version, 0);
+        assert!(load_cache(&path, dir.path(), &logger).is_empty());
+
+        let cache = CacheMap::from([(
+            PathBuf::from("a.js"),
+            CachedFile {
+                hash: "abc".to_string(),
+                config: "def".to_string(),
+                stamp: Some(FileStamp {
+                    size: 1,
+                    mtime: 2,
+                    inode: 3,
+                }),
+            },
+        )]);
+        save_cache(&path, cache.clone().into()).unwrap();
+        assert_eq!(load_cache(&path, Path::new(""), &logger), cache);
+    }
+
+    fn cached(hash: &str) -> CachedFile {
+        CachedFile {
+            hash: hash.to_string(),
+            config: "config".to_string(),
+            stamp: None,
+        }
+    }
+
+    #[test]
+    fn merges_with_entries_saved_in_the_meantime() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("cache.para");
+        let logger = Logger(Level::Error);
+        let loaded = CacheMap::from([
+            (PathBuf::from("a.js"), cached("a")),
+            (PathBuf::from("b.js"), cached("b")),
+        ]);
+        // another run updated `b.js` and added `c.js` after we loaded
+        save_cache(
+            &path,
+            CacheMap::from([
+                (PathBuf::from("a.js"), cached("a")),
+                (PathBuf::from("b.js"), cached("b2")),
+                (PathBuf::from("c.js"), cached("c")),
+            ])
+            .into(),
+        )
+        .unwrap();
+
+        let mut ours = loaded.clone();
+        ours.insert(PathBuf::from("a.js"), cached("a2"));
+        ours.insert(PathBuf::from("d.js"), cached("d"));
+        merge_cache(
+            &path,
+            Path::new(""),
+            &loaded,
+            ours,
+            RunStats::default(),
+            &logger,
+        )
+        .unwrap();
+
+        assert_eq!(
+            load_cache(&path, Path::new(""), &logger),
+            CacheMap::from([
+                (PathBuf::from("a.js"), cached("a2")),
+                (PathBuf::from("b.js"), cached("b2")),
+                (PathBuf::from("c.js"), cached("c")),
+                (PathBuf::from("d.js"), cached("d")),
+            ])
+        );
+    }
+
+    #[test]
+    fn lock_times_out_while_another_run_holds_it() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("cache.para");
+        let logger = Logger(Level::Error);
+        let held = lock_cache(&path, Duration::ZERO, &logger);
+        assert!(held.is_some());
+        assert!(lock_cache(&path, Duration::from_millis(100), &logger).is_none());
+        drop(held);
+        assert!(lock_cache(&path, Duration::ZERO, &logger).is_some());
+    }
+
+    #[test]
+    fn stores_paths_relative_to_the_project_root() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("cache.para");
+        let logger = Logger(Level::Error);
+        let (old, new) = (Path::new("/ci/job-1/app"), Path::new("/ci/job-2/app"));
+        let cache = CacheMap::from([
+            (old.join("dist/a.js"), cached("a")),
+            (PathBuf::from("/elsewhere/b.js"), cached("b")),
+        ]);
+        let last_run = RunStats { hits: 3, misses: 1 };
+        merge_cache(&path, old, &CacheMap::new(), cache, last_run, &logger).unwrap();
+
+        let stored = parse_cache(&path).unwrap();
+        assert_eq!(stored.cwd(), Some("/ci/job-1/app"));
+        assert_eq!(stored.last_run().unwrap().hit_ratio(), Some(0.75));
+        let stored = stored.to_string();
+        assert!(stored.contains("\tdist/a.js\n"), "{}", stored);
+        assert_eq!(
+            load_cache(&path, new, &logger),
+            CacheMap::from([
+                (new.join("dist/a.js"), cached("a")),
+                (PathBuf::from("/elsewhere/b.js"), cached("b")),
+            ])
+        );
+    }
+
+    #[test]
+    fn custom_cache_paths_are_files_or_directories() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let logger = Logger(Level::Error);
+        let file = dir.path().join("a/project.para");
+        let root = dir.path().join("packages/a");
+        assert_eq!(
+            generate_cache_dir(Some(&file), &root, &logger).unwrap(),
+            file
+        );
+        assert!(file.is_file());
 
-    Ok(project_cache_path)
+        // one file per project, the same wherever the repository is checked out
+        let custom = Path::new("../../node_modules/.cache/para");
+        let cache_path = |root: &Path| generate_cache_dir(Some(custom), root, &logger).unwrap();
+        let (a, b) = (
+            cache_path(&root),
+            cache_path(&dir.path().join("packages/b")),
+        );
+        assert_ne!(a, b);
+        assert_eq!(
+            a.parent(),
+            Some(dir.path().join("node_modules/.cache/para").as_path())
+        );
+        assert!(a.is_file() && b.is_file());
+        let elsewhere = tempfile::TempDir::new().unwrap();
+        let moved = cache_path(&elsewhere.path().join("packages/a"));
+        assert_eq!(moved.file_name(), a.file_name());
+
+        // a cache under a regular file is reported, not a panic
+        let under_file = file.join("cache.para");
+        assert_eq!(
+            generate_cache_dir(Some(&under_file), &root, &logger),
+            Err(under_file)
+        );
+    }
+
+    #[test]
+    fn prunes_missing_files_and_projects() {
+        let caches = tempfile::TempDir::new().unwrap();
+        let (_project, root) = crate::utils::temp_project(&[("dist/a.js", "")]);
+        let logger = Logger(Level::Error);
+        let save = |name: &str, root: &Path| {
+            let cache = CacheMap::from([
+                (root.join("dist/a.js"), cached("a")),
+                (root.join("dist/gone.js"), cached("b")),
+            ]);
+            let path = caches.path().join(name);
+            merge_cache(
+                &path,
+                root,
+                &CacheMap::new(),
+                cache,
+                RunStats::default(),
+                &logger,
+            )
+            .unwrap();
+            path
+        };
+        let live = save("live.para", root.as_std_path());
+        let gone = save("gone.para", Path::new("/no/such/project"));
+        let corrupt = caches.path().join("corrupt.para");
+        std::fs::write(&corrupt, "version = 2\ncache = \"not a list\"\n").unwrap();
+        let unrelated = caches.path().join("notes.para");
+        std::fs::write(&unrelated, "not written by para").unwrap();
+
+        let pruned = prune_caches(&live, None, &logger);
+        assert_eq!(
+            pruned,
+            Pruned {
+                entries: 1,
+                caches: 2
+            }
+        );
+        assert!(!gone.exists() && !corrupt.exists());
+        // waiting runs may still hold the lock file, so it is kept
+        assert!(gone.with_extension("para.lock").exists());
+        assert!(unrelated.exists());
+        assert_eq!(
+            load_cache(&live, root.as_std_path(), &logger),
+            CacheMap::from([(root.join("dist/a.js").into(), cached("a"))])
+        );
+        assert_eq!(list_caches(&live).len(), 2);
+
+        let pruned = prune_caches(&live, Some(Duration::ZERO), &logger);
+        assert_eq!(
+            pruned,
+            Pruned {
+                entries: 0,
+                caches: 1
+            }
+        );
+        assert!(!live.exists());
+        assert!(unrelated.exists());
+    }
 }
@@ -1,5 +1,5 @@
 use camino::Utf8PathBuf;
-use clap::{command, Parser, Subcommand, ValueEnum};
+use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(Parser)]
 #[command(
@@ -41,10 +41,6 @@ pub struct Cli {
             "node_modules".to_string(),
             ".git".to_string(),
             ".gitignore".to_string(),
-            "*.ts".to_string(),
-            "*.tsx".to_string(),
-            "*.cts".to_string(),
-            "*.mts".to_string(),
             "*.json".to_string(),
             "*.lock".to_string(),
             "*.toml".to_string(),
@@ -59,6 +55,43 @@ pub struct Cli {
     #[arg(short = 'E', long = "extend", requires = "exclude", action)]
     pub merge_with_default_exclude: bool,
 
+    /// Comma-separated patterns of the files to resolve, any other file is skipped
+    #[arg(
+        short = 'i',
+        long = "include",
+        value_name = "\x08\u{1b}[0m<\u{1b}[92mFILES\u{1b}[0m",
+        use_value_delimiter = true,
+        default_values_t = vec![
+            "*.js".to_string(),
+            "*.mjs".to_string(),
+            "*.cjs".to_string(),
+            "*.jsx".to_string(),
+            "*.d.ts".to_string(),
+            "*.d.mts".to_string(),
+            "*.d.cts".to_string(),
+        ]
+    )]
+    pub include: Vec<String>,
+
+    /// [SWITCH] Print a diff of the planned rewrites instead of writing them
+    #[arg(long = "dry-run", action)]
+    pub dry_run: bool,
+
+    /// [SWITCH] Fail instead of writing when aliased specifiers remain in the output
+    ///
+    /// Exits with 1 when specifiers need a rewrite and 2 when some cannot be resolved.
+    #[arg(long = "check", conflicts_with = "dry_run", action)]
+    pub check: bool,
+
+    /// Number of threads to walk, hash and rewrite files with [default: all cores]
+    #[arg(
+        short = 'j',
+        long = "jobs",
+        value_name = "\x08\u{1b}[0m<\u{1b}[37mN\u{1b}[0m",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub jobs: Option<usize>,
+
     /// Interact with the cache directory
     #[clap(
         help_heading = "Cache",
@@ -70,6 +103,38 @@ pub struct Cli {
     )]
     pub cache_action: Option<CacheCommand>,
 
+    /// Also delete caches not written to for this many days (with --cache prune)
+    #[arg(
+        help_heading = "Cache",
+        long = "max-age",
+        value_name = "\x08\u{1b}[0m<\u{1b}[32mDAYS\u{1b}[0m",
+        requires = "cache_action"
+    )]
+    pub max_age: Option<u64>,
+
+    /// [SWITCH] Hash every file instead of trusting unchanged size, mtime and inode
+    #[arg(help_heading = "Cache", long = "paranoid", action)]
+    pub paranoid: bool,
+
+    /// Use this cache file (*.para) or directory instead of the user cache directory
+    #[arg(
+        help_heading = "Cache",
+        long = "cache-dir",
+        env = "PARA_CACHE_DIR",
+        value_name = "\x08\u{1b}[0m<\u{1b}[32mPATH\u{1b}[0m",
+        value_hint = clap::ValueHint::AnyPath
+    )]
+    pub cache_dir: Option<std::path::PathBuf>,
+
+    /// [SWITCH] Neither read nor write the cache (overrides --cache-dir)
+    #[arg(
+        help_heading = "Cache",
+        long = "no-cache",
+        conflicts_with = "cache_action",
+        action
+    )]
+    pub no_cache: bool,
+
     /// Set the logging level
     #[arg(
         help_heading = "Verbosity",
@@ -105,4 +170,10 @@ pub enum CacheCommand {
     Dump,
     /// Print the cache directory
     Path,
+    /// Drop entries of files that are gone and caches of projects that are gone
+    Prune,
+    /// Print the size and last hit ratio of the cache
+    Stats,
+    /// List the caches of every project
+    List,
 }
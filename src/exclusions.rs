@@ -5,11 +5,11 @@ use globset::{Glob, GlobSet, GlobSetBuilder};
 ///
 /// This trait is implemented in `IntoGlobSet`
 trait IntoGlobs {
-    fn into_globs(self) -> Vec<Glob>;
+    fn into_globs(self) -> Result<Vec<Glob>, globset::Error>;
 }
 
 impl IntoGlobs for Vec<String> {
-    fn into_globs(self) -> Vec<Glob> {
+    fn into_globs(self) -> Result<Vec<Glob>, globset::Error> {
         self.iter()
             .map(|glob| {
                 Glob::new(&{
@@ -20,25 +20,45 @@ impl IntoGlobs for Vec<String> {
                     }
                     .replace(r#"\\"#, r#"/"#)
                 })
-                .unwrap()
             })
-            .collect::<Vec<_>>()
+            .collect()
     }
 }
 
+/// Decides which paths below an output directory are processed: files that
+/// match an `include` glob, unless they (or a directory above them) match an
+/// `exclude` glob.
+pub struct PathFilter {
+    pub include: GlobSet,
+    pub exclude: GlobSet,
+}
+
 pub trait IntoGlobSet {
     fn into_globset(self) -> Result<GlobSet, Error>;
 }
 
-/// Transforms the exclude list into a globset.
+/// Transforms an include or exclude list into a globset.
 impl IntoGlobSet for Vec<String> {
     fn into_globset(self) -> Result<GlobSet, Error> {
         let mut builder = GlobSetBuilder::new();
-        for glob in self.into_globs() {
+        for glob in self.into_globs()? {
             builder.add(glob);
         }
         builder
             .build()
-            .map_err(|e| anyhow!("Failed to build globset from exclusions: {}", e.to_string()))
+            .map_err(|e| anyhow!("Failed to build globset: {}", e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_globs_are_errors() {
+        let e = vec!["*.js".to_string(), "[".to_string()]
+            .into_globset()
+            .unwrap_err();
+        assert!(e.to_string().contains("unclosed character class"));
     }
 }
@@ -0,0 +1,307 @@
+use anyhow::{anyhow, bail, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use clean_path::clean;
+use json_comments::StripComments;
+use serde_json::{Map, Value};
+use std::fs::File;
+
+/// Compiler options holding a path, which TypeScript resolves relative to the
+/// config that declared them rather than the config that inherited them.
+const PATH_OPTIONS: &[&str] = &[
+    "baseUrl",
+    "outDir",
+    "rootDir",
+    "declarationDir",
+    "outFile",
+    "tsBuildInfoFile",
+];
+
+/// Compiler options holding a list of paths.
+const PATH_LIST_OPTIONS: &[&str] = &["rootDirs", "typeRoots"];
+
+/// Top level fields holding a list of paths or patterns.
+const PATH_LIST_FIELDS: &[&str] = &["files", "include", "exclude"];
+
+/// A tsconfig with its whole `extends` chain merged in.
+#[derive(Debug)]
+pub struct Extended {
+    pub value: Value,
+    /// Directory of the config that declared the effective `paths`, which
+    /// `paths` are resolved against when there is no `baseUrl`.
+    pub paths_base: Option<Utf8PathBuf>,
+}
+
+/// Reads a JSON file that may contain comments.
+pub fn read_json(path: &Utf8Path) -> Result<Value> {
+    let file = File::open(path)?;
+    let value = serde_json::from_reader(StripComments::new(std::io::BufReader::new(file)))
+        .inspect_err(|e| crate::log::error::missing_fields(path, e))?;
+    Ok(value)
+}
+
+/// Loads a tsconfig and everything it `extends`, merged with TypeScript's
+/// precedence rules: later bases override earlier ones, the config itself
+/// overrides all of its bases, and `compilerOptions` are merged key by key.
+///
+/// Paths inherited from a base are made absolute so that they still point at
+/// what the base meant. Paths declared by `path` itself are left as written.
+pub fn load_extended(path: &Utf8Path) -> Result<Extended> {
+    load(path, &mut vec![], true)
+}
+
+fn load(path: &Utf8Path, chain: &mut Vec<Utf8PathBuf>, is_leaf: bool) -> Result<Extended> {
+    if chain.iter().any(|p| p == path) {
+        bail!(
+            "Circular extends: {} -> {}",
+            chain
+                .iter()
+                .map(|p| p.as_str())
+                .collect::<Vec<_>>()
+                .join(" -> "),
+            path
+        );
+    }
+    let mut value = read_json(path)?;
+    let dir = path.parent().unwrap_or(Utf8Path::new("."));
+    if !value.is_object() {
+        bail!("Expected an object in {}", path);
+    }
+    if !is_leaf {
+        rebase(&mut value, dir);
+    }
+    let declares_paths = value
+        .pointer("/compilerOptions/paths")
+        .is_some_and(|paths| !paths.is_null());
+
+    let bases = match value.get("extends") {
+        None | Some(Value::Null) => vec![],
+        Some(Value::String(base)) => vec![base.clone()],
+        Some(Value::Array(bases)) => bases
+            .iter()
+            .map(|base| base.as_str().map(str::to_string))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| {
+                anyhow!(
+                    "\"extends\" must be a string or an array of strings in {}",
+                    path
+                )
+            })?,
+        Some(_) => bail!(
+            "\"extends\" must be a string or an array of strings in {}",
+            path
+        ),
+    };
+
+    chain.push(path.to_path_buf());
+    let mut merged = Value::Object(Map::new());
+    let mut paths_base = None;
+    for base in bases {
+        let base_path = resolve_extends(&base, dir)
+            .ok_or_else(|| anyhow!("Cannot find base config {:?} extended by {}", base, path))?;
+        let mut base = load(&base_path, chain, false)?;
+        // project references are never inherited
+        if let Some(base) = base.value.as_object_mut() {
+            base.remove("references");
+        }
+        merge(&mut merged, base.value);
+        paths_base = base.paths_base.or(paths_base);
+    }
+    chain.pop();
+
+    merge(&mut merged, value);
+    Ok(Extended {
+        value: merged,
+        paths_base: match declares_paths {
+            true => Some(dir.to_path_buf()),
+            false => paths_base,
+        },
+    })
+}
+
+/// Shallow-merges `source` into `target`, merging `compilerOptions` key by key.
+fn merge(target: &mut Value, source: Value) {
+    let (Some(target), Value::Object(source)) = (target.as_object_mut(), source) else {
+        return;
+    };
+    for (key, value) in source {
+        match (key.as_str(), target.get_mut(&key), value) {
+            ("compilerOptions", Some(Value::Object(target)), Value::Object(options)) => {
+                target.extend(options);
+            }
+            (_, _, value) => {
+                target.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Makes the path-valued fields of a base config absolute.
+fn rebase(value: &mut Value, dir: &Utf8Path) {
+    let absolute = |path: &mut Value| {
+        if let Value::String(path) = path {
+            *path = clean(dir.join(path.as_str()))
+                .to_string_lossy()
+                .into_owned();
+        }
+    };
+    if let Some(options) = value
+        .get_mut("compilerOptions")
+        .and_then(Value::as_object_mut)
+    {
+        for key in PATH_OPTIONS {
+            options.get_mut(*key).map(absolute);
+        }
+        for key in PATH_LIST_OPTIONS {
+            if let Some(Value::Array(paths)) = options.get_mut(*key) {
+                paths.iter_mut().for_each(absolute);
+            }
+        }
+    }
+    for key in PATH_LIST_FIELDS {
+        if let Some(Value::Array(paths)) = value.get_mut(*key) {
+            paths.iter_mut().for_each(absolute);
+        }
+    }
+}
+
+/// Finds the file an `extends` entry refers to.
+///
+/// Relative and absolute entries are files, with `.json` appended if needed.
+/// Anything else is a package looked up in `node_modules`, the same way
+/// TypeScript does: as a file, then through the `tsconfig` field of its
+/// `package.json`, then as `<package>/tsconfig.json`.
+pub fn resolve_extends(spec: &str, from_dir: &Utf8Path) -> Option<Utf8PathBuf> {
+    let as_file = |path: Utf8PathBuf| {
+        let path = Utf8PathBuf::from_path_buf(clean(path)).ok()?;
+        if path.is_file() {
+            return Some(path);
+        }
+        let with_json = Utf8PathBuf::from(format!("{}.json", path));
+        with_json.is_file().then_some(with_json)
+    };
+    let relative = spec.starts_with("./") || spec.starts_with("../") || spec == "." || spec == "..";
+    if relative || Utf8Path::new(spec).is_absolute() {
+        return as_file(from_dir.join(spec));
+    }
+
+    for dir in from_dir.ancestors() {
+        let package = dir.join("node_modules").join(spec);
+        if let Some(path) = as_file(package.clone()) {
+            return Some(path);
+        }
+        if let Some(tsconfig) = read_json(&package.join("package.json"))
+            .ok()
+            .and_then(|json| json.get("tsconfig")?.as_str().map(str::to_string))
+        {
+            if let Some(path) = as_file(package.join(tsconfig)) {
+                return Some(path);
+            }
+        }
+        if let Some(path) = as_file(package.join("tsconfig.json")) {
+            return Some(path);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::parse_tsconfig, utils::temp_project as project};
+
+    #[test]
+    fn inherits_paths_relative_to_the_base() {
+        let (_dir, root) = project(&[
+            (
+                "tsconfig.base.json",
+                r##"{ "compilerOptions": { "baseUrl": ".", "outDir": "dist", "rootDir": "src",
+                     "paths": { "#core": ["src/core/index"] } } }"##,
+            ),
+            (
+                "packages/app/tsconfig.json",
+                r#"{ "extends": "../../tsconfig.base", "compilerOptions": { "outDir": "lib" } }"#,
+            ),
+        ]);
+        let config = parse_tsconfig(root.join("packages/app/tsconfig.json")).unwrap();
+        assert_eq!(config.resolved_base_url.as_ref(), Some(&root));
+        assert_eq!(config.resolved_root_dir, root.join("src"));
+        assert_eq!(config.resolved_out_dir, root.join("packages/app/lib"));
+        assert_eq!(
+            config.path_map.resolve("#core").unwrap(),
+            vec![root.join("src/core/index")]
+        );
+    }
+
+    #[test]
+    fn later_bases_override_earlier_ones() {
+        let (_dir, root) = project(&[
+            (
+                "a.json",
+                r#"{ "compilerOptions": { "outDir": "a", "rootDir": "a" } }"#,
+            ),
+            (
+                "b.json",
+                r#"{ "compilerOptions": { "outDir": "b" }, "references": [] }"#,
+            ),
+            (
+                "tsconfig.json",
+                r#"{ "extends": ["./a.json", "./b.json"] }"#,
+            ),
+        ]);
+        let extended = load_extended(&root.join("tsconfig.json")).unwrap();
+        let options = &extended.value["compilerOptions"];
+        assert_eq!(options["outDir"], root.join("b").as_str());
+        assert_eq!(options["rootDir"], root.join("a").as_str());
+        assert!(extended.value.get("references").is_none());
+    }
+
+    #[test]
+    fn paths_without_base_url_are_relative_to_their_config() {
+        let (_dir, root) = project(&[
+            (
+                "configs/paths.json",
+                r#"{ "compilerOptions": { "paths": { "@/*": ["../src/*"] } } }"#,
+            ),
+            ("tsconfig.json", r#"{ "extends": "./configs/paths.json" }"#),
+        ]);
+        let extended = load_extended(&root.join("tsconfig.json")).unwrap();
+        assert_eq!(extended.paths_base, Some(root.join("configs")));
+    }
+
+    #[test]
+    fn resolves_package_bases_through_node_modules() {
+        let (_dir, root) = project(&[
+            ("node_modules/@tsconfig/node18/tsconfig.json", "{}"),
+            (
+                "node_modules/custom/package.json",
+                r#"{ "tsconfig": "base.json" }"#,
+            ),
+            ("node_modules/custom/base.json", "{}"),
+            ("node_modules/files/strict.json", "{}"),
+            ("packages/app/tsconfig.json", "{}"),
+        ]);
+        let from = root.join("packages/app");
+        assert_eq!(
+            resolve_extends("@tsconfig/node18", &from),
+            Some(root.join("node_modules/@tsconfig/node18/tsconfig.json"))
+        );
+        assert_eq!(
+            resolve_extends("custom", &from),
+            Some(root.join("node_modules/custom/base.json"))
+        );
+        assert_eq!(
+            resolve_extends("files/strict", &from),
+            Some(root.join("node_modules/files/strict.json"))
+        );
+        assert_eq!(resolve_extends("missing", &from), None);
+    }
+
+    #[test]
+    fn rejects_circular_extends() {
+        let (_dir, root) = project(&[
+            ("a.json", r#"{ "extends": "./b.json" }"#),
+            ("b.json", r#"{ "extends": "./a.json" }"#),
+        ]);
+        assert!(load_extended(&root.join("a.json")).is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::Path;
 
 use owo_colors::{AnsiColors, OwoColorize};
 
@@ -7,7 +7,7 @@ use crate::cli::Level;
 pub struct Logger(pub Level);
 
 #[allow(dead_code)]
-pub fn miss(path: &PathBuf, Logger(level): &Logger) {
+pub fn miss(path: &Path, Logger(level): &Logger) {
     match level {
         Level::Error | Level::Warn => (),
         _ => println!(
@@ -19,7 +19,7 @@ pub fn miss(path: &PathBuf, Logger(level): &Logger) {
 }
 
 #[allow(dead_code)]
-pub fn hit(path: &PathBuf, Logger(level): &Logger) {
+pub fn hit(path: &Path, Logger(level): &Logger) {
     match level {
         Level::Error | Level::Warn => (),
         _ => println!(
@@ -115,6 +115,15 @@ pub mod verbose {
         ));
     }
 
+    /// Log a candidate of an aliased specifier that has no emitted file
+    pub fn missing_candidate(specifier: &str, path: &camino::Utf8Path, logger: &super::Logger) {
+        logger.verbose(format!(
+            "{} is not emitted at {}",
+            specifier,
+            path.fg::<Cyan>().underline(),
+        ));
+    }
+
     /// Dump the cache to stdout
     pub fn dump_cache<H: Debug>(cache: H, logger: &super::Logger) {
         logger.verbose(format!("cache dump: {:#?}", cache,));
@@ -123,9 +132,9 @@ pub mod verbose {
 
 /// * Debug messages
 pub mod debug {
-    use camino::Utf8PathBuf;
+    use camino::{Utf8Path, Utf8PathBuf};
     use owo_colors::{
-        colors::{Blue, BrightBlack, Cyan, Yellow},
+        colors::{Blue, BrightBlack, Cyan, Green, Yellow},
         OwoColorize,
     };
     use std::path::Path;
@@ -147,6 +156,24 @@ pub mod debug {
         ));
     }
 
+    /// Log a file as skipped because it matched no include pattern
+    pub fn not_included(path: &Path, logger: &super::Logger) {
+        logger.debug(format!(
+            "{} {:?}",
+            "NOT INCLUDED".fg::<BrightBlack>().bold(),
+            path,
+        ));
+    }
+
+    /// Log a file as skipped because its contents are binary
+    pub fn binary_file(path: &Path, logger: &super::Logger) {
+        logger.debug(format!(
+            "{} {:?}",
+            "BINARY".fg::<BrightBlack>().bold(),
+            path,
+        ));
+    }
+
     /// Log a path as a file.
     pub fn is_file(path: &Path, logger: &super::Logger) {
         logger.debug(format!("{} {:?}", "IS FILE".fg::<Cyan>().bold(), path,));
@@ -156,12 +183,41 @@ pub mod debug {
     pub fn is_dir(path: &Path, logger: &super::Logger) {
         logger.debug(format!("{} {:?}", "IS DIR".fg::<Blue>().bold(), path,));
     }
+
+    /// Log which candidate of an aliased specifier resolved to an emitted file.
+    pub fn resolved_candidate(
+        specifier: &str,
+        index: usize,
+        candidate: &Utf8Path,
+        file: &Utf8Path,
+        logger: &super::Logger,
+    ) {
+        logger.debug(format!(
+            "{} {} via candidate #{} {:?} -> {:?}",
+            "RESOLVED".fg::<Green>().bold(),
+            specifier.fg::<Yellow>(),
+            index + 1,
+            candidate,
+            file,
+        ));
+    }
+
+    /// Log an aliased specifier and the relative specifier that replaced it.
+    pub fn rewrote_specifier(from: &str, to: &str, logger: &super::Logger) {
+        logger.debug(format!(
+            "{} {} -> {}",
+            "REWRITE".fg::<Green>().bold(),
+            from.fg::<Yellow>(),
+            to.fg::<Cyan>(),
+        ));
+    }
 }
 
 /// * Info messages
 pub mod info {
     use super::usize_success;
-    use owo_colors::OwoColorize;
+    use camino::Utf8Path;
+    use owo_colors::{colors::Cyan, OwoColorize};
 
     /// Log the quantity of successfully parsed tsconfigs
     pub fn configs_loaded(target_quantity: usize, actual_quantity: usize, logger: &super::Logger) {
@@ -170,11 +226,103 @@ pub mod info {
             actual_quantity.color(usize_success(actual_quantity, target_quantity))
         ));
     }
+
+    /// Log how many files and specifiers were (or would be) rewritten
+    pub fn summary(files: usize, specifiers: usize, dry_run: bool, logger: &super::Logger) {
+        logger.info(format!(
+            "{} {} specifiers in {} files",
+            match dry_run {
+                true => "Would rewrite",
+                false => "Rewrote",
+            },
+            specifiers.bright_green(),
+            files.bright_green()
+        ));
+    }
+
+    /// Log the outcome of `--check`
+    pub fn check_summary(needs_rewrite: usize, unresolvable: usize, logger: &super::Logger) {
+        logger.info(format!(
+            "Checked: {} specifiers need a rewrite, {} cannot be resolved",
+            needs_rewrite.color(usize_success(0, needs_rewrite)),
+            unresolvable.color(usize_success(0, unresolvable))
+        ));
+    }
+
+    /// Log a file that had its aliased specifiers rewritten
+    pub fn rewrote_file(path: &Utf8Path, rewrites: usize, logger: &super::Logger) {
+        logger.info(format!(
+            "Rewrote {} specifiers in {}",
+            rewrites.bright_green(),
+            path.fg::<Cyan>().underline()
+        ));
+    }
+}
+
+/// * Diffs
+pub mod diff {
+    use camino::Utf8Path;
+    use owo_colors::{OwoColorize, Stream::Stdout};
+    use similar::{ChangeTag, TextDiff};
+    use std::fmt::Write;
+
+    /// Write a unified diff between the original and rewritten contents of a
+    /// file to stdout, colored when the terminal supports it. Diffs are the
+    /// output of a dry run, so only `--log error` silences them.
+    ///
+    /// The diff is written at once, so that diffs of files processed in
+    /// parallel do not interleave.
+    pub fn unified(path: &Utf8Path, old: &str, new: &str, super::Logger(level): &super::Logger) {
+        if let crate::cli::Level::Error = level {
+            return;
+        }
+        print!("{}", render(path, old, new));
+    }
+
+    /// Render the unified diff that [`unified`] writes.
+    pub fn render(path: &Utf8Path, old: &str, new: &str) -> String {
+        let diff = TextDiff::from_lines(old, new);
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "{}",
+            format!("--- {}", path).if_supports_color(Stdout, |t| t.bold())
+        );
+        let _ = writeln!(
+            out,
+            "{}",
+            format!("+++ {}", path).if_supports_color(Stdout, |t| t.bold())
+        );
+        for hunk in diff.unified_diff().context_radius(3).iter_hunks() {
+            let _ = writeln!(
+                out,
+                "{}",
+                hunk.header().if_supports_color(Stdout, |t| t.cyan())
+            );
+            for change in hunk.iter_changes() {
+                let line = format!("{}{}", change.tag(), change.to_string_lossy());
+                let line = line.strip_suffix('\n').unwrap_or(&line);
+                let _ = match change.tag() {
+                    ChangeTag::Delete => {
+                        writeln!(out, "{}", line.if_supports_color(Stdout, |t| t.red()))
+                    }
+                    ChangeTag::Insert => {
+                        writeln!(out, "{}", line.if_supports_color(Stdout, |t| t.green()))
+                    }
+                    ChangeTag::Equal => writeln!(out, "{}", line),
+                };
+                if change.missing_newline() {
+                    out.push_str("\\ No newline at end of file\n");
+                }
+            }
+        }
+        out
+    }
 }
 
 /// * Warning messages
 pub mod warn {
-    use camino::Utf8PathBuf;
+    use camino::{Utf8Path, Utf8PathBuf};
     use owo_colors::{colors::Cyan, OwoColorize};
     use rayon::prelude::*;
 
@@ -187,11 +335,66 @@ pub mod warn {
     }
 
     /// Notify user when multiple paths are skipped
-    pub fn paths_skipped(paths: Vec<&Utf8PathBuf>, logger: &super::Logger) {
+    pub fn paths_skipped(paths: &[Utf8PathBuf], logger: &super::Logger) {
         paths.par_iter().for_each(|path| {
             self::skip_config(path, logger);
         });
     }
+
+    /// Notify user about options that were left to TypeScript's defaults
+    pub fn config_defaults(configs: &[crate::parser::ParaConfig], logger: &super::Logger) {
+        for config in configs {
+            let options = &config.tsconfig.compiler_options;
+            if options.out_dir.is_none() {
+                logger.warn(format!(
+                    "No \"outDir\" in {}, expecting emitted files next to their sources in {}",
+                    config.tsconfig_path.fg::<Cyan>().underline(),
+                    config.resolved_out_dir.fg::<Cyan>().underline()
+                ));
+            }
+            if options.paths.is_none() {
+                logger.warn(format!(
+                    "No \"paths\" in {}, there are no aliases to resolve",
+                    config.tsconfig_path.fg::<Cyan>().underline()
+                ));
+            }
+        }
+    }
+
+    /// Report an aliased specifier that `--check` found still in place
+    pub fn needs_rewrite(
+        path: &Utf8Path,
+        (line, column): (usize, usize),
+        specifier: &str,
+        rewrite: &str,
+        logger: &super::Logger,
+    ) {
+        logger.warn(format!(
+            "{}:{}:{} {} should be {}",
+            path.fg::<Cyan>().underline(),
+            line,
+            column,
+            specifier.yellow(),
+            rewrite.green()
+        ));
+    }
+
+    /// Notify user when the cache could not be locked against other para runs
+    pub fn cache_locked(lock_path: &std::path::Path, logger: &super::Logger) {
+        logger.warn(format!(
+            "Could not lock the cache at {}, another para run may be using it. Continuing without the lock.",
+            lock_path.display().underline()
+        ));
+    }
+
+    /// Notify user when an aliased specifier could not be resolved
+    pub fn unresolved_alias(path: &Utf8Path, specifier: &str, logger: &super::Logger) {
+        logger.warn(format!(
+            "Could not resolve {} in {}",
+            specifier.yellow(),
+            path.fg::<Cyan>().underline()
+        ));
+    }
 }
 
 /// Error messages - these don't use the logger because we don't provide a way to silence them.
@@ -205,6 +408,44 @@ pub mod error {
             path.as_ref().to_string_lossy().fg::<Cyan>().underline()
         ));
     }
+    pub fn invalid_path_pattern<P: AsRef<std::path::Path>>(path: P, key: &str) {
+        super::internal::error(format!(
+            "Invalid pattern {:?} in {}: \"paths\" keys may contain at most one '*'",
+            key,
+            path.as_ref().display()
+        ));
+    }
+    /// Report an aliased specifier that `--check` could not resolve
+    pub fn unresolvable_alias(
+        path: &camino::Utf8Path,
+        (line, column): (usize, usize),
+        specifier: &str,
+    ) {
+        super::internal::error(format!(
+            "{}:{}:{} cannot resolve {}",
+            path.fg::<Cyan>().underline(),
+            line,
+            column,
+            specifier.yellow()
+        ));
+    }
+    /// Summarize the paths that were skipped because of errors
+    pub fn failed_paths(paths: &[std::path::PathBuf]) {
+        if paths.is_empty() {
+            return;
+        }
+        let mut paths: Vec<_> = paths.iter().map(|path| path.to_string_lossy()).collect();
+        paths.sort();
+        super::internal::error(format!(
+            "Skipped {} paths after errors:\n{}",
+            paths.len().red(),
+            paths
+                .iter()
+                .map(|path| format!("  {}", path.fg::<Cyan>().underline()))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ));
+    }
     pub fn missing_fields<P: AsRef<std::path::Path>>(path: P, e: &serde_json::Error) {
         super::internal::error(format!(
             "Parsing error in {}: {}",
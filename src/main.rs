@@ -1,127 +1,160 @@
 use crate::{
     args::{handle_cli_cache_command, parse_cli_exclude_paths, parse_cli_paths},
-    cache::{load_cache, save_cache},
-    exclusions::IntoGlobSet,
+    cache::{load_cache, lock_cache, merge_cache, CacheMap, RunStats, LOCK_TIMEOUT},
+    exclusions::{IntoGlobSet, PathFilter},
     parser::load_configs,
+    pipeline::{Mode, Summary},
+    utils::Cwd,
 };
-use clap::Parser;
+use clap::{error::ErrorKind, CommandFactory, Parser};
 
-use lazy_static::lazy_static;
-use log::{debug, info, verbose, warn, Logger};
-#[allow(unused_imports)]
-use rayon::prelude::*;
-use regex::Regex;
+use log::{info, verbose, warn, Logger};
+use std::process::ExitCode;
 
 mod args;
 mod cache;
 mod cli;
 mod exclusions;
+mod extends;
 mod log;
 mod parser;
+mod pattern;
+mod pipeline;
+mod resolver;
+mod scanner;
 mod stack;
 mod utils;
 
-fn main() -> std::io::Result<()> {
+/// Exit code of `--check` when aliased specifiers still need a rewrite.
+const EXIT_NEEDS_REWRITE: u8 = 1;
+/// Exit code of `--check` when an aliased specifier cannot be resolved.
+const EXIT_UNRESOLVABLE: u8 = 2;
+/// Exit code when some paths could not be read or written.
+const EXIT_FAILED_PATHS: u8 = 3;
+
+fn main() -> ExitCode {
     let cli = cli::Cli::parse();
     let logger = Logger(cli.log_level);
-    let cache_dir = cache::generate_cache_dir(&logger)?;
+    if let Some(jobs) = cli.jobs {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global()
+            .expect("the thread pool is only built once");
+    }
+    let root = Cwd::new();
+    // Paths of the cache that could not be read or written, already logged.
+    let mut cache_failed = vec![];
+    let cache_dir = match cli.no_cache {
+        true => None,
+        false => {
+            match cache::generate_cache_dir(cli.cache_dir.as_deref(), root.as_ref(), &logger) {
+                Ok(cache_dir) => Some(cache_dir),
+                Err(path) => {
+                    cache_failed.push(path);
+                    None
+                }
+            }
+        }
+    };
 
     // Exit - if the cache command was used.
-    if let Some(_) = handle_cli_cache_command(cli.cache_action, &cache_dir, &logger) {
-        return Ok(());
+    if let Some(cache_dir) = &cache_dir {
+        if handle_cli_cache_command(cli.cache_action, cache_dir, cli.max_age, &logger).is_some() {
+            return ExitCode::SUCCESS;
+        }
+    } else if cli.cache_action.is_some() {
+        return ExitCode::from(EXIT_FAILED_PATHS);
     }
 
     // Parse - tsconfig paths
     let paths = parse_cli_paths(cli.paths, cli.paths_arg, &logger);
 
-    // Parse - exclude paths
-    let exclude_globset = parse_cli_exclude_paths(cli.exclude, cli.merge_with_default_exclude)
-        .into_globset()
-        .unwrap();
+    // Parse - include and exclude paths
+    let into_globset = |globs: Vec<String>, arg: &str| {
+        globs.into_globset().unwrap_or_else(|e| {
+            cli::Cli::command()
+                .error(ErrorKind::ValueValidation, format!("invalid {arg}: {e}"))
+                .exit()
+        })
+    };
+    let filter = PathFilter {
+        include: into_globset(cli.include, "--include"),
+        exclude: into_globset(
+            parse_cli_exclude_paths(cli.exclude, cli.merge_with_default_exclude),
+            "--exclude",
+        ),
+    };
 
     // Parse - tsconfig contents
     let (configs, skipped) = load_configs(&paths);
     verbose::tsconfigs(&configs, &logger);
-    warn::paths_skipped(skipped, &logger);
-    info::configs_loaded(paths.len(), configs.len(), &logger);
+    warn::paths_skipped(&skipped, &logger);
+    warn::config_defaults(&configs, &logger);
+    info::configs_loaded(configs.len() + skipped.len(), configs.len(), &logger);
 
     // Exit - if no configs were found
     if configs.is_empty() {
-        return Ok(());
+        return ExitCode::SUCCESS;
     }
 
-    // Init - stack
-    use stack::*;
-    let mut stack: Vec<Action> = vec![];
-    configs
-        .iter()
-        .for_each(|config| stack.push(Action::ReadDir((&config.resolved_out_dir).into())));
-
     // Init - cache
-    let mut cache = load_cache(&cache_dir, &logger).unwrap();
+    let loaded = match &cache_dir {
+        Some(cache_dir) => {
+            let _lock = lock_cache(cache_dir, LOCK_TIMEOUT, &logger);
+            load_cache(cache_dir, root.as_ref(), &logger)
+        }
+        None => CacheMap::new(),
+    };
+    let mut cache = loaded.clone();
     verbose::dump_cache(&cache, &logger);
 
-    // MAIN LOOP
-    while let Some(node) = stack.pop() {
-        if let Some(path) = node.is_match(&exclude_globset) {
-            debug::excluded_path(path, &logger);
-            continue;
-        }
-        match node {
-            Action::FinishJob(_, _) => {}
-            Action::ReadFile(path) => {
-                debug::is_file(&path, &logger);
-                let (hash, contents) = utils::hash_file(&path);
-                stack.push(Action::CompareHash(path, hash, contents));
-            }
-            Action::ReadDir(path) => {
-                debug::is_dir(&path, &logger);
-                stack.extend(
-                    path.read_dir()
-                        .expect(r#"failed to "read_dir""#)
-                        .filter_map(|entry| {
-                            let path = entry.expect("entry is invalid").path();
-                            if path.is_dir() {
-                                Some(Action::ReadDir(path))
-                            } else if path.is_file() {
-                                Some(Action::ReadFile(path))
-                            } else {
-                                None
-                            }
-                        }),
-                );
-            }
-            Action::CompareHash(path, hash, contents) => {
-                if cache.contains_key(&hash) {
-                    log::hit(&path, &logger);
-                    stack.push(Action::FinishJob(path, hash));
-                } else {
-                    log::miss(&path, &logger);
-                    cache.insert(hash.clone(), path.clone());
-                    stack.push(Action::FindCaptures(hash, contents));
-                }
-            }
-            Action::FindCaptures(hash, contents) => {
-                // todo: replace alias with resolved path
-                lazy_static! {
-                    static ref RE: Regex =
-                        Regex::new(r#"(?:require|import)\(['"](@/)([^'"]+)['"]\)"#).unwrap();
-                }
-                for _cap in RE.captures_iter(&contents) {
-                    // println!("capture: {:#?}", cap);
-                }
-                stack.push(Action::CacheFile(hash));
-            }
-            Action::CacheFile(hash) => {
-                let path = cache.get(&hash).unwrap();
-                stack.push(Action::FinishJob(path.clone(), hash.clone()));
-            }
-        }
+    // Init - summary
+    let mut summary = Summary::default();
+    let mode = match (cli.check, cli.dry_run) {
+        (true, _) => Mode::Check,
+        (_, true) => Mode::DryRun,
+        _ => Mode::Write,
+    };
+
+    for config in &configs {
+        summary += pipeline::run(config, &filter, &mut cache, mode, cli.paranoid, &logger);
     }
 
-    assert!(stack.is_empty());
-    verbose::dump_cache(&cache, &logger);
-    save_cache(&cache_dir, cache.into()).unwrap();
+    if mode == Mode::Check {
+        info::check_summary(summary.needs_rewrite, summary.unresolvable, &logger);
+    } else {
+        info::summary(
+            summary.files_changed,
+            summary.specifiers_changed,
+            mode == Mode::DryRun,
+            &logger,
+        );
+    }
+
+    // A dry run must not mark files as processed, or the real run would skip them.
+    if let (Mode::Write, Some(cache_dir)) = (mode, &cache_dir) {
+        verbose::dump_cache(&cache, &logger);
+        let last_run = RunStats {
+            hits: summary.hits,
+            misses: summary.misses,
+        };
+        if let Err(e) = merge_cache(cache_dir, root.as_ref(), &loaded, cache, last_run, &logger) {
+            log::error::os_error(cache_dir, &e);
+            cache_failed.push(cache_dir.clone());
+        }
+    }
 
-    Ok(())
+    summary.failed.extend(cache_failed);
+    log::error::failed_paths(&summary.failed);
+    // Skipped paths make any verdict incomplete, so they outrank the check.
+    match (
+        summary.failed.len(),
+        summary.needs_rewrite,
+        summary.unresolvable,
+    ) {
+        (1.., _, _) => ExitCode::from(EXIT_FAILED_PATHS),
+        (_, _, 1..) => ExitCode::from(EXIT_UNRESOLVABLE),
+        (_, 1.., _) => ExitCode::from(EXIT_NEEDS_REWRITE),
+        _ => ExitCode::SUCCESS,
+    }
 }
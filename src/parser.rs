@@ -1,10 +1,17 @@
+use crate::{
+    extends::load_extended,
+    pattern::PathMap,
+    utils::{Cwd, FileHash},
+};
 use anyhow::Result;
 use camino::{Utf8Path, Utf8PathBuf};
 use clean_path::clean;
-use json_comments::StripComments;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use indexmap::IndexMap;
 use rayon::{iter::Either, prelude::*};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fs::File, path::Path};
+use sha2::{Digest, Sha256};
+use std::{collections::HashSet, path::Path};
 
 #[derive(Default, Debug)]
 pub struct ParaConfig {
@@ -12,23 +19,160 @@ pub struct ParaConfig {
     pub tsconfig_path: Utf8PathBuf,
     pub tsconfig_parent: Utf8PathBuf,
 
+    /// `outDir`, or the root directory when unset since files are then
+    /// emitted next to their sources.
     pub resolved_out_dir: Utf8PathBuf,
-    pub resolved_base_url: Utf8PathBuf,
-    pub path_map: HashMap<String, Vec<Utf8PathBuf>>,
+    /// `declarationDir`, only when it differs from the output directory.
+    pub resolved_declaration_dir: Option<Utf8PathBuf>,
+    pub resolved_base_url: Option<Utf8PathBuf>,
+    /// `rootDir`, or the common directory of all input files when unset.
+    pub resolved_root_dir: Utf8PathBuf,
+    pub resolved_root_dirs: Vec<Utf8PathBuf>,
+    pub path_map: PathMap,
+}
+
+impl ParaConfig {
+    /// Converts a path in the source tree into the path `tsc` emits it to,
+    /// e.g. `pkg/lib.ts` -> `dist/lib.js`. Paths may omit their extension.
+    ///
+    /// Returns `None` for paths outside of the root directory, because those
+    /// are never emitted.
+    pub fn emitted_path(&self, source: &Utf8Path) -> Option<Utf8PathBuf> {
+        let relative = source.strip_prefix(&self.resolved_root_dir).ok()?;
+        let emitted = self.resolved_out_dir.join(relative);
+        if is_declaration_file(&emitted) {
+            return Some(emitted);
+        }
+        Some(match emitted.extension() {
+            Some("ts" | "tsx") => emitted.with_extension("js"),
+            Some("mts") => emitted.with_extension("mjs"),
+            Some("cts") => emitted.with_extension("cjs"),
+            _ => emitted,
+        })
+    }
+
+    /// Like [`Self::emitted_path`], but inside `declarationDir` when it is set,
+    /// e.g. `pkg/lib.ts` -> `types/lib.js`, next to the emitted `types/lib.d.ts`.
+    pub fn declaration_path(&self, source: &Utf8Path) -> Option<Utf8PathBuf> {
+        let emitted = self.emitted_path(source)?;
+        match &self.resolved_declaration_dir {
+            Some(declaration_dir) => {
+                let relative = emitted.strip_prefix(&self.resolved_out_dir).ok()?;
+                Some(declaration_dir.join(relative))
+            }
+            None => Some(emitted),
+        }
+    }
+
+    /// The directories `tsc` emits into, without the ones nested in another.
+    pub fn output_dirs(&self) -> Vec<&Utf8Path> {
+        let mut dirs = vec![self.resolved_out_dir.as_path()];
+        if let Some(declaration_dir) = &self.resolved_declaration_dir {
+            dirs.push(declaration_dir);
+        }
+        dirs.sort();
+        dirs.dedup_by(|nested, parent| nested.starts_with(parent));
+        dirs
+    }
+
+    /// Paths of the referenced projects, relative to the working directory.
+    pub fn references(&self) -> Vec<Utf8PathBuf> {
+        self.tsconfig
+            .references
+            .iter()
+            .flatten()
+            .map(|reference| {
+                Utf8PathBuf::from_path_buf(clean(self.tsconfig_parent.join(&reference.path)))
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    /// A "solution-style" tsconfig has `"files": []` and only exists to list
+    /// its references, so it has no output of its own.
+    pub fn is_solution(&self) -> bool {
+        self.tsconfig
+            .files
+            .as_ref()
+            .is_some_and(|files| files.is_empty())
+            && self.tsconfig.include.is_none()
+    }
+
+    /// A hash of everything that decides how a specifier is resolved, so that
+    /// cached results are dropped when the effective config (or para) changes.
+    ///
+    /// Paths are hashed relative to the project `root`, so that the same
+    /// project checked out somewhere else has the same fingerprint.
+    pub fn fingerprint(&self, root: &Utf8Path) -> String {
+        let mut hasher = Sha256::new();
+        let mut update = |part: &str| {
+            hasher.update(part);
+            hasher.update([0]);
+        };
+        let relative = |path: &Utf8Path| path.strip_prefix(root).unwrap_or(path).to_string();
+        update(env!("CARGO_PKG_VERSION"));
+        for (pattern, targets) in self.path_map.iter() {
+            update(&pattern.to_string());
+            targets.iter().for_each(|target| update(&relative(target)));
+        }
+        update(&format!(
+            "{:?}",
+            self.resolved_base_url.as_deref().map(relative)
+        ));
+        update(&relative(&self.resolved_root_dir));
+        for root_dir in &self.resolved_root_dirs {
+            update(&relative(root_dir));
+        }
+        update(&relative(&self.resolved_out_dir));
+        update(&format!(
+            "{:?}",
+            self.resolved_declaration_dir.as_deref().map(relative)
+        ));
+        let hash: FileHash = hasher.finalize();
+        format!("{:x}", hash)
+    }
+
+    /// Returns `source` followed by the same path inside every other `rootDirs`
+    /// entry, since TypeScript merges those directories into one virtual tree.
+    pub fn root_dirs_candidates(&self, source: &Utf8Path) -> Vec<Utf8PathBuf> {
+        let mut candidates = vec![source.to_path_buf()];
+        let Some((root, relative)) = self
+            .resolved_root_dirs
+            .iter()
+            .find_map(|root| Some((root, source.strip_prefix(root).ok()?)))
+        else {
+            return candidates;
+        };
+        candidates.extend(
+            self.resolved_root_dirs
+                .iter()
+                .filter(|other| *other != root)
+                .map(|other| other.join(relative)),
+        );
+        candidates
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct Tsconfig {
-    #[serde(rename = "compilerOptions")]
+    #[serde(rename = "compilerOptions", default)]
     pub compiler_options: CompilerOptions,
+    pub files: Option<Vec<String>>,
     pub include: Option<Vec<String>>,
     pub exclude: Option<Vec<String>>,
+    pub references: Option<Vec<ProjectReference>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ProjectReference {
+    /// A tsconfig, or a directory containing a `tsconfig.json`.
+    pub path: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct CompilerOptions {
     #[serde(rename = "baseUrl")]
-    pub base_url: String,
+    pub base_url: Option<String>,
     /// Defines the structure of the dist directory.
     ///
     /// ### Project Structure
@@ -68,36 +212,40 @@ pub struct CompilerOptions {
     ///     └── bar.js
     /// ```
     ///
-    #[serde(rename = "rootDir", default)]
-    pub root_dir: String,
+    #[serde(rename = "rootDir")]
+    pub root_dir: Option<String>,
     #[serde(rename = "rootDirs", default)]
     pub root_dirs: Vec<String>,
     #[serde(rename = "outDir")]
-    pub out_dir: String,
-    pub paths: HashMap<String, Vec<String>>,
+    pub out_dir: Option<String>,
+    /// Where `.d.ts` files are emitted instead of next to their JavaScript.
+    #[serde(rename = "declarationDir")]
+    pub declaration_dir: Option<String>,
+    /// Since TypeScript 4.1 `paths` may be used without `baseUrl`, in which
+    /// case they are relative to the tsconfig that declared them.
+    pub paths: Option<IndexMap<String, Vec<String>>>,
 }
 
 pub fn parse_tsconfig<P>(path: P) -> Result<ParaConfig>
 where
     P: AsRef<Path>,
 {
+    // Resolve everything against an absolute path so that rewritten specifiers
+    // can be computed between any two paths in the project.
     let tsconfig_path = Utf8Path::from_path(path.as_ref()).unwrap();
-    let file = File::open(tsconfig_path)?;
-    let tsconfig: Tsconfig = serde_json::from_reader(StripComments::new(std::io::BufReader::new(
-        file,
-    )))
-    .map_err(|e| {
-        crate::log::error::missing_fields(tsconfig_path, &e);
-        e
-    })?;
+    let tsconfig_path = &Utf8PathBuf::from_path_buf(clean(Cwd::new().join(tsconfig_path))).unwrap();
+    let extended = load_extended(tsconfig_path)?;
+    let tsconfig: Tsconfig = serde_json::from_value(extended.value)
+        .inspect_err(|e| crate::log::error::missing_fields(tsconfig_path, e))?;
     let tsconfig_parent = tsconfig_path.parent().unwrap();
-    let (resolved_base_url, resolved_out_dir) = {
-        let r = clean(tsconfig_parent.join(&tsconfig.compiler_options.base_url));
-        (
-            Utf8PathBuf::from_path_buf(r.clone()).unwrap(),
-            Utf8PathBuf::from_path_buf(r).unwrap(),
-        )
-    };
+    let resolve =
+        |path: &str| Utf8PathBuf::from_path_buf(clean(tsconfig_parent.join(path))).unwrap();
+    let options = &tsconfig.compiler_options;
+    let resolved_base_url = options.base_url.as_deref().map(resolve);
+    let resolved_out_dir = options.out_dir.as_deref().map(resolve);
+    let resolved_declaration_dir = options.declaration_dir.as_deref().map(resolve);
+    let resolved_root_dirs = options.root_dirs.iter().map(|dir| resolve(dir)).collect();
+    let resolved_root_dir = options.root_dir.as_deref().map(resolve);
 
     // ? ---
     let mut para_config = ParaConfig {
@@ -105,34 +253,200 @@ where
         tsconfig_path: tsconfig_path.into(),
         tsconfig_parent: tsconfig_parent.into(),
         resolved_base_url,
-        resolved_out_dir,
-        path_map: HashMap::new(),
+        resolved_out_dir: Utf8PathBuf::new(),
+        resolved_declaration_dir: None,
+        resolved_root_dir: Utf8PathBuf::new(),
+        resolved_root_dirs,
+        path_map: PathMap::default(),
     };
 
-    let alias_map = create_alias_path_map(&para_config.tsconfig, &para_config.tsconfig_parent);
+    para_config.resolved_root_dir = resolved_root_dir.unwrap_or_else(|| {
+        let files = source_files(
+            &para_config.tsconfig,
+            &para_config.tsconfig_parent,
+            resolved_out_dir.as_deref(),
+        );
+        common_source_dir(&files).unwrap_or_else(|| para_config.tsconfig_parent.clone())
+    });
+    para_config.resolved_out_dir =
+        resolved_out_dir.unwrap_or_else(|| para_config.resolved_root_dir.clone());
+    para_config.resolved_declaration_dir =
+        resolved_declaration_dir.filter(|dir| *dir != para_config.resolved_out_dir);
+
+    // `paths` are relative to `baseUrl`, or to the config that declared them.
+    let paths_base = match &para_config.resolved_base_url {
+        Some(base_url) => base_url.clone(),
+        None => extended
+            .paths_base
+            .unwrap_or_else(|| para_config.tsconfig_parent.clone()),
+    };
+    let alias_map = create_alias_path_map(&para_config.tsconfig, &paths_base);
     para_config.path_map = alias_map;
 
     Ok(para_config)
 }
 
-pub fn create_alias_path_map(
+pub fn create_alias_path_map(tsconfig: &Tsconfig, paths_base: &Utf8Path) -> PathMap {
+    let Some(paths) = &tsconfig.compiler_options.paths else {
+        return PathMap::default();
+    };
+    let (path_map, invalid) = PathMap::new(paths, paths_base);
+    for key in invalid {
+        crate::log::error::invalid_path_pattern(paths_base, &key);
+    }
+    path_map
+}
+
+/// Whether a path names a declaration file (`.d.ts`, `.d.mts` or `.d.cts`).
+pub fn is_declaration_file(path: &Utf8Path) -> bool {
+    let name = path.file_name().unwrap_or_default();
+    name.ends_with(".d.ts") || name.ends_with(".d.mts") || name.ends_with(".d.cts")
+}
+
+/// Directories that are never part of a TypeScript program.
+const IGNORED_DIRS: &[&str] = &["node_modules", "bower_components", "jspm_packages"];
+
+/// Builds a globset from `include`/`exclude` style specs, relative to `base`.
+/// A spec whose last component has no wildcard or extension names a directory.
+///
+/// Like `tsc`, wildcards of `include` specs never match a name that starts
+/// with a dot (e.g. `.storybook`), `exclude` specs match anything.
+fn spec_globset(specs: &[String], base: &Utf8Path, include: bool) -> GlobSet {
+    let mut builder = GlobSetBuilder::new();
+    for spec in specs {
+        let spec = Utf8PathBuf::from_path_buf(clean(base.join(spec))).unwrap();
+        let last = spec.file_name().unwrap_or_default();
+        let mut patterns = vec![spec.to_string()];
+        if !last.contains('*') && !last.contains('.') {
+            patterns.push(spec.join("**/*").to_string());
+        }
+        if include {
+            patterns = patterns.iter().map(|p| without_dotted_matches(p)).collect();
+        }
+        for pattern in patterns {
+            if let Ok(glob) = GlobBuilder::new(&pattern).literal_separator(true).build() {
+                builder.add(glob);
+            }
+        }
+    }
+    builder.build().unwrap_or_else(|_| GlobSet::empty())
+}
+
+/// Keeps the leading `*` or `?` of a component from matching a dot. `**` is
+/// left alone, dotted directories are simply not walked into.
+fn without_dotted_matches(pattern: &str) -> String {
+    pattern
+        .split('/')
+        .map(|component| match component.strip_prefix(['*', '?']) {
+            Some(_) if component == "**" => component.to_string(),
+            Some(rest) if component.starts_with('*') => format!("[!.]*{rest}"),
+            Some(rest) => format!("[!.]{rest}"),
+            None => component.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The directory a spec can match files in: everything before the first
+/// component with a wildcard.
+fn spec_base_dir(spec: &str, base: &Utf8Path) -> Utf8PathBuf {
+    let mut dir = base.to_path_buf();
+    for component in Utf8Path::new(spec).components() {
+        if component.as_str().contains(['*', '?']) {
+            break;
+        }
+        dir.push(component);
+    }
+    Utf8PathBuf::from_path_buf(clean(dir)).unwrap()
+}
+
+/// Lists the TypeScript files a tsconfig compiles, following `files`,
+/// `include` and `exclude`. Declaration files are not inputs that get
+/// emitted, so they are left out.
+pub fn source_files(
     tsconfig: &Tsconfig,
     tsconfig_parent: &Utf8Path,
-) -> HashMap<String, Vec<Utf8PathBuf>> {
-    let mut alias_map: HashMap<String, Vec<Utf8PathBuf>> = HashMap::new();
-    for (alias, paths) in &tsconfig.compiler_options.paths {
-        let alias = alias.trim_end_matches("/*");
-        let paths = paths
-            .iter()
-            .map(|path| {
-                let path = path.trim_end_matches("/*");
-                let path = clean(tsconfig_parent.join(path));
-                Utf8PathBuf::from_path_buf(path).unwrap()
-            })
-            .collect::<Vec<_>>();
-        alias_map.insert(alias.to_owned(), paths);
+    out_dir: Option<&Utf8Path>,
+) -> Vec<Utf8PathBuf> {
+    let is_input = |path: &Utf8Path| {
+        matches!(path.extension(), Some("ts" | "tsx" | "mts" | "cts")) && !is_declaration_file(path)
+    };
+    let mut files = tsconfig
+        .files
+        .iter()
+        .flatten()
+        .map(|file| Utf8PathBuf::from_path_buf(clean(tsconfig_parent.join(file))).unwrap())
+        .filter(|file| is_input(file))
+        .collect::<Vec<_>>();
+
+    let include = match (&tsconfig.include, &tsconfig.files) {
+        (Some(include), _) => include.clone(),
+        (None, Some(_)) => vec![],
+        (None, None) => vec!["**/*".to_string()],
+    };
+    if include.is_empty() {
+        return files;
+    }
+    // only walk the directories the include specs can match in
+    let mut dirs = include
+        .iter()
+        .map(|spec| spec_base_dir(spec, tsconfig_parent))
+        .collect::<Vec<_>>();
+    dirs.sort();
+    dirs.dedup_by(|nested, parent| nested.starts_with(parent));
+    // wildcards skip dotted directories, so only walk the ones a spec names
+    let dotted: HashSet<&str> = include
+        .iter()
+        .flat_map(|spec| spec.split('/'))
+        .filter(|component| component.starts_with('.') && !matches!(*component, "." | ".."))
+        .collect();
+    let include_set = spec_globset(&include, tsconfig_parent, true);
+    let exclude = spec_globset(
+        tsconfig.exclude.as_deref().unwrap_or_default(),
+        tsconfig_parent,
+        false,
+    );
+
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = dir.read_dir_utf8() else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.into_path();
+            if exclude.is_match(&path) {
+                continue;
+            }
+            let name = path.file_name().unwrap_or_default();
+            if path.is_dir() {
+                if Some(path.as_path()) != out_dir
+                    && !IGNORED_DIRS.contains(&name)
+                    && (!name.starts_with('.') || dotted.contains(name))
+                {
+                    dirs.push(path);
+                }
+            } else if is_input(&path) && include_set.is_match(&path) {
+                files.push(path);
+            }
+        }
     }
-    alias_map
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// The longest common directory of a set of files, which is where `tsc` roots
+/// the output tree when `rootDir` is not set.
+pub fn common_source_dir(files: &[Utf8PathBuf]) -> Option<Utf8PathBuf> {
+    let mut files = files.iter();
+    let mut common = files.next()?.parent()?.to_path_buf();
+    for file in files {
+        while !file.starts_with(&common) {
+            if !common.pop() {
+                break;
+            }
+        }
+    }
+    Some(common)
 }
 
 /// This will append a file name to a path if the path is a directory, otherwise returns the path.
@@ -144,23 +458,39 @@ pub fn normalize_dir_paths(path: &Utf8PathBuf, file: impl AsRef<Utf8Path>) -> Ut
     path
 }
 
-pub fn load_configs(paths: &[Utf8PathBuf]) -> (Vec<ParaConfig>, Vec<&Utf8PathBuf>) {
+/// Loads every tsconfig in `paths` along with all of the projects they
+/// reference, each one only once. Solution-style configs are followed but not
+/// returned. Returns the loaded configs and the paths that failed to load.
+pub fn load_configs(paths: &[Utf8PathBuf]) -> (Vec<ParaConfig>, Vec<Utf8PathBuf>) {
     let default_tsconfig_name: &str = "tsconfig.json";
-    paths.par_iter().partition_map(|path| {
-        match parse_tsconfig(normalize_dir_paths(path, default_tsconfig_name)) {
-            Ok(config) => Either::Left(config),
-            Err(e) => {
-                crate::log::error::os_error(path, &e);
-                Either::Right(path)
+    let mut configs = vec![];
+    let mut skipped = vec![];
+    let mut seen = HashSet::new();
+    let mut pending = paths.to_vec();
+    while !pending.is_empty() {
+        pending.retain(|path| {
+            let path = normalize_dir_paths(path, default_tsconfig_name);
+            seen.insert(clean(Cwd::new().join(path)))
+        });
+        let (loaded, failed): (Vec<_>, Vec<_>) = pending.par_iter().partition_map(|path| {
+            match parse_tsconfig(normalize_dir_paths(path, default_tsconfig_name)) {
+                Ok(config) => Either::Left(config),
+                Err(e) => {
+                    crate::log::error::os_error(path, &e);
+                    Either::Right(path.clone())
+                }
             }
-        }
-    })
+        });
+        skipped.extend(failed);
+        pending = loaded.iter().flat_map(ParaConfig::references).collect();
+        configs.extend(loaded.into_iter().filter(|config| !config.is_solution()));
+    }
+    (configs, skipped)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::utils::Cwd;
 
     #[test]
     fn default_to_tsconfig_dot_json() {
@@ -178,15 +508,87 @@ mod tests {
     #[test]
     fn cannot_parse_invalid_tsconfig_paths() {
         let cwd = Cwd::new();
-        let config = parse_tsconfig(&cwd.join("./tsconfig.json"));
+        let config = parse_tsconfig(cwd.join("./tsconfig.json"));
         assert!(config.is_err());
     }
     #[test]
     fn cannot_parse_malformed_tsconfigs() {
+        let (_dir, root) = crate::utils::temp_project(&[
+            ("tsconfig.json", r#"{ "compilerOptions": { "outDir": 1 } }"#),
+            ("jsconfig.json", r#"{ "compilerOptions": "#),
+        ]);
+        assert!(parse_tsconfig(root.join("tsconfig.json")).is_err());
+        assert!(parse_tsconfig(root.join("jsconfig.json")).is_err());
+    }
+
+    #[test]
+    fn should_parse_tsconfigs_without_out_dir() {
         let cwd = Cwd::new();
-        let b = cwd.clone().join("b_tsconfig.jsonc");
-        let b_config = parse_tsconfig(&b).ok();
-        assert!(b_config.is_none());
+        let config = parse_tsconfig(cwd.join("b_tsconfig.jsonc")).unwrap();
+        assert_eq!(config.tsconfig.compiler_options.out_dir, None);
+        // files are emitted next to their sources
+        assert_eq!(config.resolved_out_dir, config.resolved_root_dir);
+    }
+
+    #[test]
+    fn declaration_dir_is_another_output_dir() {
+        let parse = |declaration_dir: &str| {
+            let tsconfig = format!(
+                r#"{{ "compilerOptions": {{ "rootDir": "src", "outDir": "dist", "declarationDir": "{}" }} }}"#,
+                declaration_dir
+            );
+            let (dir, root) = crate::utils::temp_project(&[("tsconfig.json", &tsconfig)]);
+            let config = parse_tsconfig(root.join("tsconfig.json")).unwrap();
+            let output_dirs: Vec<_> = config
+                .output_dirs()
+                .iter()
+                .map(|dir| dir.strip_prefix(&root).unwrap().to_string())
+                .collect();
+            let declared = config
+                .declaration_path(&root.join("src/a.ts"))
+                .map(|path| path.strip_prefix(&root).unwrap().to_string());
+            drop(dir);
+            (output_dirs, declared)
+        };
+        assert_eq!(
+            parse("types"),
+            (
+                vec!["dist".into(), "types".into()],
+                Some("types/a.js".into())
+            )
+        );
+        assert_eq!(
+            parse("./dist"),
+            (vec!["dist".into()], Some("dist/a.js".into()))
+        );
+        assert_eq!(parse("dist/types").0, vec!["dist".to_string()]);
+    }
+
+    #[test]
+    fn paths_keep_their_declaration_order() {
+        let (_dir, root) = crate::utils::temp_project(&[(
+            "tsconfig.json",
+            r#"{ "compilerOptions": { "baseUrl": ".", "paths": { "*.svg": ["assets/*.svg"], "*": ["types/*"] } } }"#,
+        )]);
+        let config = parse_tsconfig(root.join("tsconfig.json")).unwrap();
+        assert_eq!(
+            config.path_map.resolve("logo.svg").unwrap(),
+            vec![root.join("assets/logo.svg")]
+        );
+    }
+
+    #[test]
+    fn paths_without_base_url_are_relative_to_the_tsconfig() {
+        let (_dir, root) = crate::utils::temp_project(&[(
+            "tsconfig.json",
+            r#"{ "compilerOptions": { "paths": { "@/*": ["./src/*"] } } }"#,
+        )]);
+        let config = parse_tsconfig(root.join("tsconfig.json")).unwrap();
+        assert_eq!(config.resolved_base_url, None);
+        assert_eq!(
+            config.path_map.resolve("@/lib").unwrap(),
+            vec![root.join("src/lib")]
+        );
     }
 
     #[test]
@@ -196,11 +598,12 @@ mod tests {
             cwd.join("a_tsconfig.jsonc"),
             cwd.join("b_tsconfig.jsonc"),
             cwd.join("myapp/tsconfig.json"),
+            cwd.join("missing/tsconfig.json"),
         ];
         let (configs, errors) = load_configs(tsconfig_paths);
-        assert_eq!(configs.len(), 2);
+        assert_eq!(configs.len(), 3);
         assert_eq!(errors.len(), 1);
-        assert_eq!(errors[0], &tsconfig_paths[1])
+        assert_eq!(errors[0], tsconfig_paths[3])
     }
 
     #[test]
@@ -209,23 +612,188 @@ mod tests {
         let config = parse_tsconfig(cwd.join("myapp/tsconfig.json")).unwrap();
 
         // Tsconfig deserialization
-        assert_eq!(config.tsconfig.compiler_options.base_url, "./");
-        assert_eq!(config.tsconfig.compiler_options.out_dir, "./dist");
-        assert_eq!(config.tsconfig.compiler_options.paths.len(), 2);
+        let options = &config.tsconfig.compiler_options;
+        assert_eq!(options.base_url.as_deref(), Some("./"));
+        assert_eq!(options.out_dir.as_deref(), Some("./dist"));
+        let paths = options.paths.as_ref().unwrap();
+        assert_eq!(paths.len(), 2);
+        assert_eq!(paths["@/*"], vec!["pkg/*", "pkg/other/*"]);
+        assert_eq!(paths["$/*"], vec!["node_modules/*"]);
+        assert_eq!(config.tsconfig_path, cwd.join("myapp/tsconfig.json"));
+        assert_eq!(config.tsconfig_parent, cwd.join("myapp"));
+        // - resolved_base_url
+        assert_eq!(config.resolved_base_url, Some(cwd.join("myapp/")));
+        // - resolved_root_dir
+        assert_eq!(config.resolved_root_dir, cwd.join("myapp/pkg"));
+        // - resolved_out_dir
+        assert_eq!(config.resolved_out_dir, cwd.join("myapp/dist"));
+        // - root_dirs
+        assert!(config.resolved_root_dirs.is_empty());
+    }
+
+    #[test]
+    fn should_map_found_tsconfig_paths() {
+        let cwd = Cwd::new();
+        let config = parse_tsconfig(cwd.join("myapp/tsconfig.json")).unwrap();
         assert_eq!(
-            config.tsconfig.compiler_options.paths["@/*"],
-            vec!["pkg/*", "pkg/other/*"]
+            config.path_map.resolve("@/lib").unwrap(),
+            vec![cwd.join("myapp/pkg/lib"), cwd.join("myapp/pkg/other/lib")]
         );
         assert_eq!(
-            config.tsconfig.compiler_options.paths["$/*"],
-            vec!["node_modules/*"]
+            config.path_map.resolve("$/typescript").unwrap(),
+            vec![cwd.join("myapp/node_modules/typescript")]
         );
-        assert_eq!(config.tsconfig_path, cwd.join("myapp/tsconfig.json"));
-        assert_eq!(config.tsconfig_parent, cwd.join("myapp"));
-        // - resolved_base_url
-        assert_eq!(config.resolved_base_url, cwd.join("myapp/"));
-        // - resolved_root_dir // todo
-        // - resolved_out_dir // todo
-        // - thinking on root_dirs... // todo
+        assert!(config.path_map.resolve("./lib").is_none());
+    }
+
+    #[test]
+    fn fingerprint_changes_with_the_effective_config() {
+        let tsconfig =
+            r#"{ "compilerOptions": { "outDir": "dist", "paths": { "@/*": ["src/*"] } } }"#;
+        let (_dir, root) = crate::utils::temp_project(&[
+            ("a/tsconfig.json", tsconfig),
+            ("b/tsconfig.json", tsconfig),
+        ]);
+        let fingerprint = |dir: &str| {
+            parse_tsconfig(root.join(dir).join("tsconfig.json"))
+                .unwrap()
+                .fingerprint(&root.join(dir))
+        };
+        let a = fingerprint("a");
+        assert_eq!(a, fingerprint("a"));
+        // the same project in another place
+        assert_eq!(a, fingerprint("b"));
+        assert_ne!(
+            a,
+            parse_tsconfig(root.join("a/tsconfig.json"))
+                .unwrap()
+                .fingerprint(&root)
+        );
+
+        std::fs::write(
+            root.join("a/tsconfig.json"),
+            tsconfig.replace("src/*", "lib/*"),
+        )
+        .unwrap();
+        assert_ne!(a, fingerprint("a"));
+    }
+
+    #[test]
+    fn should_map_source_paths_into_out_dir() {
+        let cwd = Cwd::new();
+        let config = parse_tsconfig(cwd.join("myapp/tsconfig.json")).unwrap();
+        let emitted = |path: &str| config.emitted_path(&cwd.join(path));
+        assert_eq!(
+            emitted("myapp/pkg/lib.ts"),
+            Some(cwd.join("myapp/dist/lib.js"))
+        );
+        assert_eq!(
+            emitted("myapp/pkg/other"),
+            Some(cwd.join("myapp/dist/other"))
+        );
+        assert_eq!(
+            emitted("myapp/pkg/a.mts"),
+            Some(cwd.join("myapp/dist/a.mjs"))
+        );
+        assert_eq!(
+            emitted("myapp/pkg/a.d.ts"),
+            Some(cwd.join("myapp/dist/a.d.ts"))
+        );
+        assert_eq!(emitted("myapp/node_modules/x"), None);
+    }
+
+    #[test]
+    fn common_source_dir_is_the_longest_shared_parent() {
+        let files = ["/p/src/a/x.ts", "/p/src/a/b/y.ts", "/p/src/c/z.ts"].map(Utf8PathBuf::from);
+        assert_eq!(common_source_dir(&files), Some(Utf8PathBuf::from("/p/src")));
+        assert_eq!(
+            common_source_dir(&files[..1]),
+            Some(Utf8PathBuf::from("/p/src/a"))
+        );
+        assert_eq!(common_source_dir(&[]), None);
+    }
+
+    #[test]
+    fn wildcards_skip_dotted_names() {
+        let (_dir, root) = crate::utils::temp_project(&[
+            (
+                "tsconfig.json",
+                r#"{ "compilerOptions": { "outDir": "dist", "paths": { "@/*": ["src/*"] } } }"#,
+            ),
+            ("src/lib.ts", ""),
+            ("src/a/x.ts", ""),
+            ("src/a/.x.ts", ""),
+            (".storybook/main.ts", ""),
+        ]);
+        let config = parse_tsconfig(root.join("tsconfig.json")).unwrap();
+        assert_eq!(config.resolved_root_dir, root.join("src"));
+
+        // unless a spec names them
+        let tsconfig = Tsconfig {
+            include: Some(vec!["src/**/*".into(), ".storybook/*.ts".into()]),
+            ..Default::default()
+        };
+        assert_eq!(
+            source_files(&tsconfig, &root, None),
+            vec![
+                root.join(".storybook/main.ts"),
+                root.join("src/a/x.ts"),
+                root.join("src/lib.ts")
+            ]
+        );
+    }
+
+    #[test]
+    fn root_dirs_are_merged_into_one_tree() {
+        let config = ParaConfig {
+            resolved_root_dirs: vec!["/p/src".into(), "/p/generated".into()],
+            ..Default::default()
+        };
+        assert_eq!(
+            config.root_dirs_candidates(Utf8Path::new("/p/src/schema")),
+            vec![
+                Utf8PathBuf::from("/p/src/schema"),
+                "/p/generated/schema".into()
+            ]
+        );
+        assert_eq!(
+            config.root_dirs_candidates(Utf8Path::new("/p/lib/x")),
+            vec![Utf8PathBuf::from("/p/lib/x")]
+        );
+    }
+
+    #[test]
+    fn should_follow_project_references() {
+        let (_dir, root) = crate::utils::temp_project(&[
+            (
+                "tsconfig.json",
+                r#"{ "files": [], "references": [
+                    { "path": "./packages/a" },
+                    { "path": "./packages/b/tsconfig.build.json" }
+                ] }"#,
+            ),
+            (
+                "packages/a/tsconfig.json",
+                r#"{ "references": [{ "path": "../b/tsconfig.build.json" }, { "path": "../c" }] }"#,
+            ),
+            (
+                "packages/b/tsconfig.build.json",
+                r#"{ "references": [{ "path": "../a" }] }"#,
+            ),
+        ]);
+        let (configs, errors) = load_configs(std::slice::from_ref(&root));
+        let mut loaded = configs
+            .iter()
+            .map(|c| c.tsconfig_path.clone())
+            .collect::<Vec<_>>();
+        loaded.sort();
+        assert_eq!(
+            loaded,
+            vec![
+                root.join("packages/a/tsconfig.json"),
+                root.join("packages/b/tsconfig.build.json")
+            ]
+        );
+        assert_eq!(errors, vec![root.join("packages/c")]);
     }
 }
@@ -0,0 +1,233 @@
+use camino::{Utf8Path, Utf8PathBuf};
+use clean_path::clean;
+use indexmap::IndexMap;
+
+/// A key of `compilerOptions.paths`, e.g. `@/*`, `config` or `@app/*/public`.
+///
+/// TypeScript allows at most one `*` anywhere in a key. A key without a `*`
+/// only matches a specifier that is exactly equal to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathPattern {
+    prefix: String,
+    /// The text after the `*`, or `None` for an exact pattern.
+    suffix: Option<String>,
+}
+
+impl PathPattern {
+    /// Parses a `paths` key. Keys with more than one `*` are invalid.
+    pub fn parse(key: &str) -> Option<Self> {
+        match key.split_once('*') {
+            None => Some(Self {
+                prefix: key.to_string(),
+                suffix: None,
+            }),
+            Some((_, suffix)) if suffix.contains('*') => None,
+            Some((prefix, suffix)) => Some(Self {
+                prefix: prefix.to_string(),
+                suffix: Some(suffix.to_string()),
+            }),
+        }
+    }
+
+    pub fn is_exact(&self) -> bool {
+        self.suffix.is_none()
+    }
+
+    /// Length of the text before the `*`, used to pick the most specific
+    /// pattern when several match.
+    pub fn prefix_len(&self) -> usize {
+        self.prefix.len()
+    }
+
+    /// Matches a specifier against this pattern, returning the text matched by
+    /// the `*` (always empty for exact patterns).
+    pub fn matches<'a>(&self, specifier: &'a str) -> Option<&'a str> {
+        match &self.suffix {
+            None => (specifier == self.prefix).then_some(""),
+            Some(suffix) => {
+                if specifier.len() < self.prefix.len() + suffix.len() {
+                    return None;
+                }
+                specifier
+                    .strip_prefix(&self.prefix)?
+                    .strip_suffix(suffix.as_str())
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for PathPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.suffix {
+            None => write!(f, "{}", self.prefix),
+            Some(suffix) => write!(f, "{}*{}", self.prefix, suffix),
+        }
+    }
+}
+
+/// The resolved `compilerOptions.paths` of a tsconfig.
+#[derive(Debug, Default)]
+pub struct PathMap {
+    /// Patterns and their targets, already joined onto the base path. Targets
+    /// may still contain the `*` to substitute.
+    entries: Vec<(PathPattern, Vec<Utf8PathBuf>)>,
+}
+
+impl PathMap {
+    /// Builds a path map from `paths`, resolving targets against `base`.
+    /// Invalid keys are returned so they can be reported.
+    pub fn new(paths: &IndexMap<String, Vec<String>>, base: &Utf8Path) -> (Self, Vec<String>) {
+        let mut invalid = vec![];
+        let entries = paths
+            .iter()
+            .filter_map(|(key, targets)| match PathPattern::parse(key) {
+                Some(pattern) => Some((
+                    pattern,
+                    targets.iter().map(|target| base.join(target)).collect(),
+                )),
+                None => {
+                    invalid.push(key.clone());
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        (Self { entries }, invalid)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The patterns and their targets, in declaration order.
+    pub fn iter(&self) -> impl Iterator<Item = (&PathPattern, &[Utf8PathBuf])> {
+        self.entries
+            .iter()
+            .map(|(pattern, targets)| (pattern, targets.as_slice()))
+    }
+
+    /// Finds the pattern TypeScript would use for a specifier: an exact match
+    /// wins, otherwise the wildcard pattern with the longest prefix, or the
+    /// first one declared of those.
+    pub fn find<'a>(&self, specifier: &'a str) -> Option<(&PathPattern, &[Utf8PathBuf], &'a str)> {
+        if let Some((pattern, targets)) = self
+            .entries
+            .iter()
+            .find(|(pattern, _)| pattern.is_exact() && pattern.matches(specifier).is_some())
+        {
+            return Some((pattern, targets, ""));
+        }
+        let mut best: Option<(&PathPattern, &[Utf8PathBuf], &'a str)> = None;
+        for (pattern, targets) in self.entries.iter().filter(|(p, _)| !p.is_exact()) {
+            if let Some(star) = pattern.matches(specifier) {
+                if best.is_none_or(|(b, _, _)| pattern.prefix_len() > b.prefix_len()) {
+                    best = Some((pattern, targets, star));
+                }
+            }
+        }
+        best
+    }
+
+    /// Returns the candidate paths for a specifier, in declaration order, with
+    /// the matched text substituted for the `*` in each target.
+    pub fn resolve(&self, specifier: &str) -> Option<Vec<Utf8PathBuf>> {
+        let (_, targets, star) = self.find(specifier)?;
+        Some(
+            targets
+                .iter()
+                .map(|target| {
+                    let target = target.as_str().replacen('*', star, 1);
+                    Utf8PathBuf::from_path_buf(clean(target)).unwrap()
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path_map(paths: &[(&str, &[&str])]) -> PathMap {
+        let paths = paths
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.iter().map(|s| s.to_string()).collect()))
+            .collect();
+        let (map, invalid) = PathMap::new(&paths, Utf8Path::new("/proj"));
+        assert!(invalid.is_empty());
+        map
+    }
+
+    #[test]
+    fn parses_wildcards_anywhere_in_the_key() {
+        let pattern = PathPattern::parse("@app/*/public").unwrap();
+        assert_eq!(pattern.matches("@app/ui/public"), Some("ui"));
+        assert_eq!(pattern.matches("@app/ui/private"), None);
+        assert_eq!(pattern.matches("@app/public"), None);
+
+        let pattern = PathPattern::parse("*").unwrap();
+        assert_eq!(pattern.matches("anything"), Some("anything"));
+
+        let pattern = PathPattern::parse("config").unwrap();
+        assert!(pattern.is_exact());
+        assert_eq!(pattern.matches("config"), Some(""));
+        assert_eq!(pattern.matches("config/x"), None);
+
+        assert!(PathPattern::parse("@/*/*").is_none());
+    }
+
+    #[test]
+    fn prefix_and_suffix_may_not_overlap() {
+        // TypeScript requires the specifier to be at least as long as the
+        // prefix and suffix combined.
+        let pattern = PathPattern::parse("ab*ba").unwrap();
+        assert_eq!(pattern.matches("aba"), None);
+        assert_eq!(pattern.matches("abba"), Some(""));
+    }
+
+    #[test]
+    fn exact_match_wins_over_wildcards() {
+        let map = path_map(&[("*", &["a/*"]), ("config", &["src/config/index.ts"])]);
+        assert_eq!(
+            map.resolve("config").unwrap(),
+            vec![Utf8PathBuf::from("/proj/src/config/index.ts")]
+        );
+    }
+
+    #[test]
+    fn longest_prefix_wins() {
+        let map = path_map(&[
+            ("@/*", &["pkg/*"]),
+            ("@utils/*", &["pkg/utils/*"]),
+            ("*", &["types/*"]),
+        ]);
+        assert_eq!(map.resolve("@utils/fmt").unwrap(), ["/proj/pkg/utils/fmt"]);
+        assert_eq!(map.resolve("@/lib").unwrap(), ["/proj/pkg/lib"]);
+        assert_eq!(map.resolve("lodash").unwrap(), ["/proj/types/lodash"]);
+    }
+
+    #[test]
+    fn first_declared_wins_between_equal_prefixes() {
+        // `findBestPatternMatch` only replaces a match with a longer prefix
+        let svg: &[(&str, &[&str])] = &[("*.svg", &["assets/*.svg"]), ("*", &["types/*"])];
+        let map = path_map(svg);
+        assert_eq!(map.resolve("logo.svg").unwrap(), ["/proj/assets/logo.svg"]);
+        let reversed: Vec<_> = svg.iter().rev().copied().collect();
+        let map = path_map(&reversed);
+        assert_eq!(map.resolve("logo.svg").unwrap(), ["/proj/types/logo.svg"]);
+    }
+
+    #[test]
+    fn substitutes_the_wildcard_into_every_target() {
+        let map = path_map(&[
+            ("@app/*/public", &["packages/*/src/public", "vendor/*"]),
+            ("#core", &["src/core/index"]),
+        ]);
+        assert_eq!(
+            map.resolve("@app/ui/public").unwrap(),
+            ["/proj/packages/ui/src/public", "/proj/vendor/ui"]
+        );
+        assert_eq!(map.resolve("#core").unwrap(), ["/proj/src/core/index"]);
+        assert!(map.resolve("#coreutils").is_none());
+        assert!(map.resolve("@scope/pkg").is_none());
+    }
+}
@@ -0,0 +1,688 @@
+use crate::{
+    cache::{CacheMap, CachedFile, FileStamp},
+    exclusions::PathFilter,
+    log::{self, debug, info, warn, Logger},
+    parser::ParaConfig,
+    resolver,
+    stack::*,
+    utils,
+};
+use camino::Utf8Path;
+use rayon::prelude::*;
+use std::path::PathBuf;
+
+/// What to do with the aliased specifiers that are found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Rewrite them in place.
+    Write,
+    /// Print a diff of the rewrites instead of writing them.
+    DryRun,
+    /// Report them without touching any file or the cache.
+    Check,
+}
+
+/// Counts of everything a run did (or would have done).
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Summary {
+    pub hits: usize,
+    pub misses: usize,
+    pub files_changed: usize,
+    pub specifiers_changed: usize,
+    pub needs_rewrite: usize,
+    pub unresolvable: usize,
+    /// Paths that could not be read or written, each logged as it failed.
+    pub failed: Vec<PathBuf>,
+}
+
+impl std::ops::AddAssign for Summary {
+    fn add_assign(&mut self, other: Self) {
+        self.hits += other.hits;
+        self.misses += other.misses;
+        self.files_changed += other.files_changed;
+        self.specifiers_changed += other.specifiers_changed;
+        self.needs_rewrite += other.needs_rewrite;
+        self.unresolvable += other.unresolvable;
+        self.failed.extend(other.failed);
+    }
+}
+
+/// Walks the output directories of a config (`outDir` and `declarationDir`)
+/// and resolves the aliased specifiers
+/// of every file that the filter lets through and that is not already cached.
+/// Binary files are never decoded, let alone rewritten.
+///
+/// A cached file whose size, mtime and inode are unchanged is not even read,
+/// unless `paranoid` is set, in which case every file is hashed.
+///
+/// Directories are walked and files are processed on the rayon thread pool;
+/// only the resulting cache entries are merged back on the calling thread.
+///
+/// A directory or file that cannot be read (or written) is logged, skipped
+/// and recorded in [`Summary::failed`], the rest of the run goes on.
+pub fn run(
+    config: &ParaConfig,
+    filter: &PathFilter,
+    cache: &mut CacheMap,
+    mode: Mode,
+    paranoid: bool,
+    logger: &Logger,
+) -> Summary {
+    let fingerprint = config.fingerprint(&utils::Cwd::new());
+    let job = Job {
+        config,
+        cache,
+        fingerprint: &fingerprint,
+        mode,
+        paranoid,
+        logger,
+    };
+
+    // Declarations may not have been emitted (yet), but the output must exist.
+    let dirs: Vec<_> = config
+        .output_dirs()
+        .into_iter()
+        .filter(|dir| *dir == config.resolved_out_dir || dir.is_dir())
+        .collect();
+    let files: Vec<_> = dirs
+        .into_par_iter()
+        .flat_map(|dir| walk(Action::ReadDir(dir.into()), filter, logger))
+        .collect();
+    let outcomes: Vec<Outcome> = files
+        .into_par_iter()
+        .map(|file| match file {
+            Ok(path) => job.process(path),
+            Err(dir) => Outcome::failed(dir),
+        })
+        .collect();
+
+    // Merge - single-threaded, in the order the files were walked
+    let mut summary = Summary::default();
+    for outcome in outcomes {
+        summary += outcome.summary;
+        if let Some((path, file)) = outcome.cached {
+            cache.insert(path, file);
+        }
+    }
+    summary
+}
+
+/// Lists every file below a `ReadDir` node that the filter lets through,
+/// reading sibling directories in parallel.
+///
+/// Directories that cannot be read (entirely or in part) are listed as `Err`.
+fn walk(node: Action, filter: &PathFilter, logger: &Logger) -> Vec<Result<PathBuf, PathBuf>> {
+    if let Some(path) = node.is_match(&filter.exclude) {
+        debug::excluded_path(path, logger);
+        return vec![];
+    }
+    match node {
+        Action::ReadFile(path) if filter.include.is_match(&path) => vec![Ok(path)],
+        Action::ReadFile(path) => {
+            debug::not_included(&path, logger);
+            vec![]
+        }
+        Action::ReadDir(path) => {
+            debug::is_dir(&path, logger);
+            let entries = match path.read_dir() {
+                Ok(entries) => entries,
+                Err(e) => {
+                    log::error::os_error(&path, &e.into());
+                    return vec![Err(path)];
+                }
+            };
+            let mut failed = false;
+            let children: Vec<Action> = entries
+                .filter_map(|entry| match entry {
+                    Ok(entry) => Some(entry.path()),
+                    Err(e) => {
+                        log::error::os_error(&path, &e.into());
+                        failed = true;
+                        None
+                    }
+                })
+                .filter_map(|path| {
+                    if path.is_dir() {
+                        Some(Action::ReadDir(path))
+                    } else if path.is_file() {
+                        Some(Action::ReadFile(path))
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+            let mut files: Vec<_> = children
+                .into_par_iter()
+                .flat_map(|node| walk(node, filter, logger))
+                .collect();
+            if failed {
+                files.push(Err(path));
+            }
+            files
+        }
+        _ => unreachable!("only paths are walked"),
+    }
+}
+
+/// Everything a worker needs to process a single file.
+struct Job<'a> {
+    config: &'a ParaConfig,
+    cache: &'a CacheMap,
+    fingerprint: &'a str,
+    mode: Mode,
+    paranoid: bool,
+    logger: &'a Logger,
+}
+
+/// What processing a single file did, to be merged into the run.
+#[derive(Default)]
+struct Outcome {
+    summary: Summary,
+    cached: Option<(PathBuf, CachedFile)>,
+}
+
+impl Outcome {
+    fn failed(path: PathBuf) -> Self {
+        let mut outcome = Self::default();
+        outcome.summary.failed.push(path);
+        outcome
+    }
+}
+
+impl Job<'_> {
+    /// Runs the actions of a single file, from reading it to caching it.
+    fn process(&self, path: PathBuf) -> Outcome {
+        let Job {
+            config,
+            cache,
+            fingerprint,
+            mode,
+            paranoid,
+            logger,
+        } = *self;
+        let mut outcome = Outcome::default();
+        let summary = &mut outcome.summary;
+        // The metadata of the file as it was read (or written), for `CacheFile`.
+        let mut stamp = None;
+
+        let mut stack: Vec<Action> = vec![Action::ReadFile(path)];
+        while let Some(node) = stack.pop() {
+            match node {
+                Action::FinishJob(_, _) => {}
+                Action::ReadFile(path) => {
+                    debug::is_file(&path, logger);
+                    stamp = FileStamp::of(&path);
+                    let unchanged = cache.get(&path).filter(|file| {
+                        stamp.is_some() && file.stamp == stamp && file.config == fingerprint
+                    });
+                    if let (Some(file), false, false) = (unchanged, paranoid, mode == Mode::Check) {
+                        log::hit(&path, logger);
+                        summary.hits += 1;
+                        let hash = file.hash.clone();
+                        stack.push(Action::FinishJob(path, hash));
+                        continue;
+                    }
+                    let (hash, contents) = match utils::hash_file(&path) {
+                        Ok(file) => file,
+                        Err(e) => {
+                            log::error::os_error(&path, &e.into());
+                            summary.failed.push(path);
+                            continue;
+                        }
+                    };
+                    stack.push(Action::CompareHash(path, hash, contents));
+                }
+                Action::ReadDir(_) => unreachable!("directories are walked beforehand"),
+                Action::CompareHash(path, hash, contents) => {
+                    // A check must see every file, including the ones already processed.
+                    if mode == Mode::Check {
+                        stack.push(Action::FindCaptures(path, hash, contents));
+                    } else if cache
+                        .get(&path)
+                        .is_some_and(|file| file.hash == hash && file.config == fingerprint)
+                    {
+                        log::hit(&path, logger);
+                        summary.hits += 1;
+                        // e.g. touched without changes, so next time the metadata matches again
+                        stack.push(Action::CacheFile(path, hash));
+                    } else {
+                        log::miss(&path, logger);
+                        summary.misses += 1;
+                        stack.push(Action::FindCaptures(path, hash, contents));
+                    }
+                }
+                Action::FindCaptures(path, hash, contents) => {
+                    // Files that are binary or not UTF-8 cannot hold specifiers we could rewrite.
+                    let binary = utils::is_binary(&contents);
+                    if binary {
+                        debug::binary_file(&path, logger);
+                    }
+                    let (Some(importer), false, Ok(source)) = (
+                        Utf8Path::from_path(&path),
+                        binary,
+                        std::str::from_utf8(&contents),
+                    ) else {
+                        stack.push(match mode {
+                            Mode::Check => Action::FinishJob(path, hash),
+                            _ => Action::CacheFile(path, hash),
+                        });
+                        continue;
+                    };
+                    if mode == Mode::Check {
+                        for alias in resolver::find_aliases(config, importer, source, logger) {
+                            let position = utils::line_col(source, alias.specifier.range.start);
+                            match alias.rewrite {
+                                Some(rewrite) => {
+                                    warn::needs_rewrite(
+                                        importer,
+                                        position,
+                                        alias.specifier.value,
+                                        &rewrite,
+                                        logger,
+                                    );
+                                    summary.needs_rewrite += 1;
+                                }
+                                None => {
+                                    log::error::unresolvable_alias(
+                                        importer,
+                                        position,
+                                        alias.specifier.value,
+                                    );
+                                    summary.unresolvable += 1;
+                                }
+                            }
+                        }
+                        stack.push(Action::FinishJob(path, hash));
+                        continue;
+                    }
+                    let (rewritten, rewrites, unresolved) =
+                        resolver::rewrite_source(config, importer, source, logger);
+                    if rewrites == 0 {
+                        // Retry unresolved aliases next time, their targets may be emitted by then.
+                        stack.push(match unresolved {
+                            0 => Action::CacheFile(path, hash),
+                            _ => Action::FinishJob(path, hash),
+                        });
+                        continue;
+                    }
+                    if mode == Mode::DryRun {
+                        log::diff::unified(importer, source, &rewritten, logger);
+                    } else if let Err(e) = utils::write_atomic(&path, rewritten.as_bytes()) {
+                        // the original is intact, so it must not be cached as rewritten
+                        log::error::os_error(&path, &e.into());
+                        summary.failed.push(path.clone());
+                        stack.push(Action::FinishJob(path, hash));
+                        continue;
+                    } else {
+                        info::rewrote_file(importer, rewrites, logger);
+                        stamp = FileStamp::of(&path);
+                    }
+                    summary.files_changed += 1;
+                    summary.specifiers_changed += rewrites;
+                    // Remember the file as we left it, so that it is a hit until
+                    // it changes again (e.g. when `tsc` emits it anew).
+                    let hash = utils::hash_bytes(rewritten.as_bytes());
+                    stack.push(match unresolved {
+                        0 => Action::CacheFile(path, hash),
+                        _ => Action::FinishJob(path, hash),
+                    });
+                }
+                Action::CacheFile(path, hash) => {
+                    // Only what is on disk is worth remembering, a dry run changed nothing.
+                    if mode == Mode::Write {
+                        let file = CachedFile {
+                            hash: hash.clone(),
+                            config: fingerprint.to_string(),
+                            stamp: stamp.take(),
+                        };
+                        outcome.cached = Some((path.clone(), file));
+                    }
+                    stack.push(Action::FinishJob(path, hash));
+                }
+            }
+        }
+
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cli::Level, exclusions::IntoGlobSet, parser::parse_tsconfig, utils::Cwd};
+    use globset::GlobSet;
+    use std::collections::BTreeMap;
+
+    /// Copies the `myapp` fixture into a temporary directory along with the
+    /// output `tsc` emits for it, which still holds the aliased specifiers.
+    fn myapp() -> (tempfile::TempDir, camino::Utf8PathBuf) {
+        let cwd = Cwd::new();
+        let fixture = |path: &str| std::fs::read_to_string(cwd.join("myapp").join(path)).unwrap();
+        let tsconfig = fixture("tsconfig.json");
+        let (index, lib, other) = (
+            fixture("pkg/index.ts"),
+            fixture("pkg/lib.ts"),
+            fixture("pkg/other/index.ts"),
+        );
+        let lib_js = lib.replace("...args: any[]", "...args");
+        utils::temp_project(&[
+            ("tsconfig.json", &tsconfig),
+            ("pkg/index.ts", &index),
+            ("pkg/lib.ts", &lib),
+            ("pkg/other/index.ts", &other),
+            ("dist/index.js", &index),
+            ("dist/lib.js", &lib_js),
+            ("dist/other/index.js", &other),
+            ("node_modules/path/index.js", ""),
+        ])
+    }
+
+    /// Lets every `.js` file through.
+    fn javascript() -> PathFilter {
+        PathFilter {
+            include: vec!["*.js".to_string()].into_globset().unwrap(),
+            exclude: GlobSet::empty(),
+        }
+    }
+
+    fn snapshot(dir: &Utf8Path) -> BTreeMap<String, Vec<u8>> {
+        let mut files = BTreeMap::new();
+        let mut dirs = vec![dir.to_path_buf()];
+        while let Some(dir) = dirs.pop() {
+            for entry in dir.read_dir_utf8().unwrap() {
+                let path = entry.unwrap().into_path();
+                match path.is_dir() {
+                    true => dirs.push(path),
+                    false => {
+                        files.insert(path.to_string(), std::fs::read(&path).unwrap());
+                    }
+                }
+            }
+        }
+        files
+    }
+
+    /// Runs the pipeline over a config, quietly.
+    fn run_with(
+        config: &ParaConfig,
+        filter: &PathFilter,
+        cache: &mut CacheMap,
+        mode: Mode,
+        paranoid: bool,
+    ) -> Summary {
+        run(config, filter, cache, mode, paranoid, &Logger(Level::Error))
+    }
+
+    #[test]
+    fn second_run_over_myapp_changes_nothing() {
+        let (_dir, root) = myapp();
+        let config = parse_tsconfig(root.join("tsconfig.json")).unwrap();
+        // a fresh cache every time, as after `--cache clear`
+        let run_once = || {
+            run_with(
+                &config,
+                &javascript(),
+                &mut CacheMap::new(),
+                Mode::Write,
+                false,
+            )
+        };
+
+        let first = run_once();
+        assert_eq!(first.files_changed, 1);
+        assert_eq!(first.specifiers_changed, 2);
+        let index = std::fs::read_to_string(root.join("dist/index.js")).unwrap();
+        assert!(index
+            .starts_with("import { log } from \"./lib\";\nimport { demo } from \"./other\";\n"));
+
+        let after_first = snapshot(&root);
+        let second = run_once();
+        assert_eq!(
+            second,
+            Summary {
+                misses: 3,
+                ..Summary::default()
+            }
+        );
+        assert_eq!(snapshot(&root), after_first);
+    }
+
+    #[test]
+    fn unchanged_metadata_skips_hashing_unless_paranoid() {
+        let (_dir, root) = myapp();
+        let config = parse_tsconfig(root.join("tsconfig.json")).unwrap();
+        let mut cache = CacheMap::new();
+        let mut run_once =
+            |paranoid: bool| run_with(&config, &javascript(), &mut cache, Mode::Write, paranoid);
+        run_once(false);
+        assert_eq!(run_once(false).hits, 3);
+
+        // same size, same mtime, same inode, different contents
+        let index = root.join("dist/index.js");
+        let mtime = std::fs::metadata(&index).unwrap().modified().unwrap();
+        let contents = std::fs::read_to_string(&index)
+            .unwrap()
+            .replace("./lib", "@/lib");
+        std::fs::write(&index, contents).unwrap();
+        let file = std::fs::File::options().write(true).open(&index).unwrap();
+        file.set_modified(mtime).unwrap();
+        drop(file);
+
+        assert_eq!(run_once(false).hits, 3);
+        let paranoid = run_once(true);
+        assert_eq!((paranoid.hits, paranoid.files_changed), (2, 1));
+    }
+
+    #[test]
+    fn parallel_runs_match_a_single_threaded_run() {
+        let mut files = vec![(
+            "tsconfig.json".to_string(),
+            r#"{ "compilerOptions": { "baseUrl": ".", "paths": { "@/*": ["src/*"] }, "outDir": "dist" } }"#
+                .to_string(),
+        )];
+        for i in 0..8 {
+            for j in 0..16 {
+                let source = format!("import {{ x }} from \"@/m{}/f{}\";\n", (i + 1) % 8, j);
+                files.push((format!("src/m{i}/f{j}.ts"), source.clone()));
+                files.push((format!("dist/m{i}/f{j}.js"), source));
+            }
+        }
+        let files: Vec<(&str, &str)> = files.iter().map(|(p, c)| (&**p, &**c)).collect();
+
+        let run_on = |threads: usize| {
+            let (dir, root) = utils::temp_project(&files);
+            let config = parse_tsconfig(root.join("tsconfig.json")).unwrap();
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            let mut cache = CacheMap::new();
+            let summary =
+                pool.install(|| run_with(&config, &javascript(), &mut cache, Mode::Write, false));
+            let relative = |path: String| path.replacen(root.as_str(), "", 1);
+            let snapshot: BTreeMap<_, _> = snapshot(&root)
+                .into_iter()
+                .map(|(path, contents)| (relative(path), contents))
+                .collect();
+            drop(dir);
+            (summary, cache.len(), snapshot)
+        };
+
+        let (summary, cached, snapshot) = run_on(1);
+        assert_eq!(
+            (summary.files_changed, summary.specifiers_changed),
+            (128, 128)
+        );
+        assert_eq!(cached, 128);
+        assert_eq!(run_on(4), (summary, cached, snapshot));
+    }
+
+    #[test]
+    fn unreadable_paths_are_reported_and_skipped() {
+        let (_dir, root) = myapp();
+        let config = parse_tsconfig(root.join("tsconfig.json")).unwrap();
+        let logger = Logger(Level::Error);
+        let missing = root.join("dist/missing").into_std_path_buf();
+
+        let walked = walk(Action::ReadDir(missing.clone()), &javascript(), &logger);
+        assert_eq!(walked, vec![Err(missing.clone())]);
+
+        // e.g. deleted between the walk and the read
+        let job = Job {
+            config: &config,
+            cache: &CacheMap::new(),
+            fingerprint: "",
+            mode: Mode::Write,
+            paranoid: false,
+            logger: &logger,
+        };
+        let outcome = job.process(missing.join("index.js"));
+        assert_eq!(outcome.summary.failed, vec![missing.join("index.js")]);
+        assert!(outcome.cached.is_none());
+    }
+
+    #[test]
+    fn skips_binary_and_non_javascript_files() {
+        let (_dir, root) = myapp();
+        let config = parse_tsconfig(root.join("tsconfig.json")).unwrap();
+        let binary = b"import { log } from \"@/lib\";\0";
+        std::fs::write(root.join("dist/blob.js"), binary).unwrap();
+        std::fs::write(root.join("dist/index.js.map"), "{\"sources\":[\"@/lib\"]}").unwrap();
+
+        let mut cache = CacheMap::new();
+        let summary = run_with(&config, &javascript(), &mut cache, Mode::Write, false);
+
+        assert_eq!(summary.files_changed, 1);
+        assert_eq!(std::fs::read(root.join("dist/blob.js")).unwrap(), binary);
+        assert!(cache.contains_key(root.join("dist/blob.js").as_std_path()));
+        assert!(!cache.contains_key(root.join("dist/index.js.map").as_std_path()));
+    }
+
+    #[test]
+    fn rewrites_declarations_in_declaration_dir() {
+        let tsconfig = r#"{ "compilerOptions": { "baseUrl": ".", "rootDir": "src", "outDir": "dist",
+            "declarationDir": "types", "paths": { "@/*": ["src/*"] } } }"#;
+        let (_dir, root) = utils::temp_project(&[
+            ("tsconfig.json", tsconfig),
+            ("src/index.ts", ""),
+            ("src/lib.ts", ""),
+            ("dist/index.js", "export * from \"@/lib\";\n"),
+            ("dist/lib.js", ""),
+            ("types/index.d.ts", "export * from \"@/lib\";\n"),
+            ("types/lib.d.ts", ""),
+        ]);
+        let config = parse_tsconfig(root.join("tsconfig.json")).unwrap();
+        let filter = PathFilter {
+            include: vec!["*.js".to_string(), "*.d.ts".to_string()]
+                .into_globset()
+                .unwrap(),
+            exclude: GlobSet::empty(),
+        };
+        let summary = run_with(&config, &filter, &mut CacheMap::new(), Mode::Write, false);
+
+        assert_eq!(summary.files_changed, 2);
+        for path in ["dist/index.js", "types/index.d.ts"] {
+            let contents = std::fs::read_to_string(root.join(path)).unwrap();
+            assert_eq!(contents, "export * from \"./lib\";\n");
+        }
+    }
+
+    #[test]
+    fn dry_run_changes_no_file_and_caches_nothing() {
+        let (_dir, root) = myapp();
+        let config = parse_tsconfig(root.join("tsconfig.json")).unwrap();
+        let before = snapshot(&root);
+        let mut cache = CacheMap::new();
+        let summary = run_with(&config, &javascript(), &mut cache, Mode::DryRun, false);
+
+        assert_eq!(snapshot(&root), before);
+        assert!(cache.is_empty());
+        assert_eq!((summary.files_changed, summary.specifiers_changed), (1, 2));
+
+        let index = root.join("dist/index.js");
+        let old = std::fs::read_to_string(&index).unwrap();
+        let new = old.replace("@/lib", "./lib").replace("@/other", "./other");
+        let diff = log::diff::render(&index, &old, &new);
+        assert!(diff.contains(&format!("--- {}", index)));
+        assert!(diff.contains("@@ -1,"));
+        assert!(diff.contains("-import { log } from \"@/lib\";"));
+        assert!(diff.contains("+import { log } from \"./lib\";"));
+        assert!(diff.contains("+import { demo } from \"./other\";"));
+    }
+
+    #[test]
+    fn identical_files_in_different_directories_are_both_rewritten() {
+        let (_dir, root) = myapp();
+        let config = parse_tsconfig(root.join("tsconfig.json")).unwrap();
+        let source = "import { log } from \"@/lib\";\n";
+        std::fs::create_dir_all(root.join("dist/a")).unwrap();
+        std::fs::write(root.join("dist/a/x.js"), source).unwrap();
+        let mut cache = CacheMap::new();
+        let mut run_once = || run_with(&config, &javascript(), &mut cache, Mode::Write, false);
+        run_once();
+
+        // same contents as `a/x.js` had, but another place to point `../lib` from
+        std::fs::create_dir_all(root.join("dist/b")).unwrap();
+        std::fs::write(root.join("dist/b/x.js"), source).unwrap();
+        let second = run_once();
+        assert_eq!((second.misses, second.files_changed), (1, 1));
+        for path in ["dist/a/x.js", "dist/b/x.js"] {
+            let contents = std::fs::read_to_string(root.join(path)).unwrap();
+            assert_eq!(contents, "import { log } from \"../lib\";\n");
+        }
+    }
+
+    #[test]
+    fn changing_paths_invalidates_cached_files() {
+        let (_dir, root) = myapp();
+        let mut cache = CacheMap::new();
+        let mut run_once =
+            |config: &ParaConfig| run_with(config, &javascript(), &mut cache, Mode::Write, false);
+        let config = parse_tsconfig(root.join("tsconfig.json")).unwrap();
+        run_once(&config);
+        assert_eq!(run_once(&config).hits, 3);
+
+        let tsconfig = std::fs::read_to_string(root.join("tsconfig.json")).unwrap();
+        let tsconfig = tsconfig.replace(r#""$/*""#, r#""~/*""#);
+        std::fs::write(root.join("tsconfig.json"), tsconfig).unwrap();
+        let changed = parse_tsconfig(root.join("tsconfig.json")).unwrap();
+        let summary = run_once(&changed);
+        assert_eq!((summary.hits, summary.misses), (0, 3));
+    }
+
+    #[test]
+    fn rewritten_files_are_cached_as_written() {
+        let (_dir, root) = myapp();
+        let config = parse_tsconfig(root.join("tsconfig.json")).unwrap();
+        let mut cache = CacheMap::new();
+        let mut run_once =
+            |paranoid: bool| run_with(&config, &javascript(), &mut cache, Mode::Write, paranoid);
+        assert_eq!(run_once(false).files_changed, 1);
+
+        // hashing every file again, the rewritten one must match its entry too
+        let paranoid = run_once(true);
+        assert_eq!((paranoid.hits, paranoid.misses), (3, 0));
+        let index = std::fs::read(root.join("dist/index.js")).unwrap();
+        let cached = &cache[root.join("dist/index.js").as_std_path()];
+        assert_eq!(cached.hash, utils::hash_bytes(&index));
+    }
+
+    #[test]
+    fn files_with_unresolved_aliases_are_not_cached() {
+        let (_dir, root) = myapp();
+        let config = parse_tsconfig(root.join("tsconfig.json")).unwrap();
+        std::fs::create_dir_all(root.join("dist/a")).unwrap();
+        std::fs::write(root.join("dist/a/x.js"), "require(\"@/missing\");\n").unwrap();
+        let mut cache = CacheMap::new();
+        let mut run_once = || run_with(&config, &javascript(), &mut cache, Mode::Write, false);
+        run_once();
+
+        // e.g. emitted by a later `tsc` run
+        std::fs::write(root.join("pkg/missing.ts"), "").unwrap();
+        std::fs::write(root.join("dist/missing.js"), "").unwrap();
+        let second = run_once();
+        assert_eq!((second.hits, second.files_changed), (3, 1));
+        let contents = std::fs::read_to_string(root.join("dist/a/x.js")).unwrap();
+        assert_eq!(contents, "require(\"../missing\");\n");
+    }
+}
@@ -0,0 +1,627 @@
+use crate::{
+    log::{debug, verbose, warn, Logger},
+    parser::{is_declaration_file, ParaConfig},
+    scanner::{scan_specifiers, Specifier},
+    utils::{relative_path, to_relative_specifier},
+};
+use camino::{Utf8Path, Utf8PathBuf};
+
+/// Extensions that an emitted specifier may omit, in the order they are tried.
+const EMITTED_EXTENSIONS: &[&str] = &["js", "mjs", "cjs", "jsx", "json"];
+
+/// Finds the emitted file a specifier pointing at `path` would load: the path
+/// itself, the path with an emitted extension, or a directory index.
+pub fn probe_emitted(path: &Utf8Path) -> Option<Utf8PathBuf> {
+    if path.is_file() {
+        return Some(path.to_path_buf());
+    }
+    let with_extension = |path: &Utf8Path| {
+        EMITTED_EXTENSIONS
+            .iter()
+            .map(|ext| Utf8PathBuf::from(format!("{}.{}", path, ext)))
+            .find(|path| path.is_file())
+    };
+    with_extension(path).or_else(|| match path.is_dir() {
+        true => with_extension(&path.join("index")),
+        false => None,
+    })
+}
+
+/// Extensions of declaration files that a specifier may omit, in the order
+/// they are tried.
+const DECLARATION_EXTENSIONS: &[&str] = &["d.ts", "d.mts", "d.cts"];
+
+/// Finds the declaration file a specifier pointing at `path` would load: the
+/// path itself, the declaration of an emitted `.js`/`.mjs`/`.cjs` path, the
+/// path with a declaration extension, or a directory index.
+pub fn probe_declaration(path: &Utf8Path) -> Option<Utf8PathBuf> {
+    if path.is_file() {
+        return Some(path.to_path_buf());
+    }
+    let declaration = match path.extension() {
+        Some("js") => Some(path.with_extension("d.ts")),
+        Some("mjs") => Some(path.with_extension("d.mts")),
+        Some("cjs") => Some(path.with_extension("d.cts")),
+        _ => None,
+    };
+    if let Some(declaration) = declaration.filter(|path| path.is_file()) {
+        return Some(declaration);
+    }
+    let with_extension = |path: &Utf8Path| {
+        DECLARATION_EXTENSIONS
+            .iter()
+            .map(|ext| Utf8PathBuf::from(format!("{}.{}", path, ext)))
+            .find(|path| path.is_file())
+    };
+    with_extension(path).or_else(|| match path.is_dir() {
+        true => with_extension(&path.join("index")),
+        false => None,
+    })
+}
+
+/// Tries each candidate of an aliased specifier in order and returns the
+/// emitted path of the first one that exists in `outDir`.
+///
+/// Candidates outside of the root directory are not emitted by `tsc`, so they
+/// are looked up where they live instead (e.g. an alias into `node_modules`).
+///
+/// Specifiers in a `declaration` file point at the emitted declarations first,
+/// which live in `declarationDir` when it is set.
+pub fn resolve_alias(
+    config: &ParaConfig,
+    specifier: &str,
+    candidates: &[Utf8PathBuf],
+    declaration: bool,
+    logger: &Logger,
+) -> Option<Utf8PathBuf> {
+    let candidates = candidates
+        .iter()
+        .flat_map(|candidate| config.root_dirs_candidates(candidate));
+    candidates.enumerate().find_map(|(i, candidate)| {
+        let emitted = config
+            .emitted_path(&candidate)
+            .unwrap_or_else(|| candidate.clone());
+        let declared = declaration
+            .then(|| {
+                let declared = config
+                    .declaration_path(&candidate)
+                    .unwrap_or_else(|| candidate.clone());
+                Some((probe_declaration(&declared)?, declared))
+            })
+            .flatten();
+        match declared.or_else(|| Some((probe_emitted(&emitted)?, emitted.clone()))) {
+            Some((file, target)) => {
+                debug::resolved_candidate(specifier, i, &candidate, &file, logger);
+                Some(target)
+            }
+            None => {
+                verbose::missing_candidate(specifier, &emitted, logger);
+                None
+            }
+        }
+    })
+}
+
+/// Rewrites an aliased specifier into a `./` or `../` specifier relative to
+/// the file that imports it.
+pub fn rewrite_specifier(
+    config: &ParaConfig,
+    importer: &Utf8Path,
+    specifier: &str,
+    candidates: &[Utf8PathBuf],
+    logger: &Logger,
+) -> Option<String> {
+    let declaration = is_declaration_file(importer);
+    let target = resolve_alias(config, specifier, candidates, declaration, logger)?;
+    let from = importer.parent()?;
+    Some(to_relative_specifier(&relative_path(from, &target)))
+}
+
+/// Whether TypeScript would look a specifier up in `paths` at all. Relative
+/// and absolute specifiers, and URLs such as `node:fs`, never are.
+pub fn is_aliasable(specifier: &str) -> bool {
+    let is_relative = specifier == "."
+        || specifier == ".."
+        || specifier.starts_with("./")
+        || specifier.starts_with("../");
+    // a scheme is at least two characters, so that `C:` stays a Windows path
+    let has_scheme = specifier.split_once(':').is_some_and(|(scheme, _)| {
+        scheme.len() > 1
+            && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    });
+    !(is_relative
+        || has_scheme
+        || specifier.starts_with('/')
+        || specifier.starts_with('\\')
+        || Utf8Path::new(specifier).is_absolute())
+}
+
+/// The modules built into Node.js (`require("module").builtinModules`),
+/// which can be imported without their `node:` prefix.
+const NODE_BUILTINS: &[&str] = &[
+    "_http_agent",
+    "_http_client",
+    "_http_common",
+    "_http_incoming",
+    "_http_outgoing",
+    "_http_server",
+    "_stream_duplex",
+    "_stream_passthrough",
+    "_stream_readable",
+    "_stream_transform",
+    "_stream_wrap",
+    "_stream_writable",
+    "_tls_common",
+    "_tls_wrap",
+    "assert",
+    "assert/strict",
+    "async_hooks",
+    "buffer",
+    "child_process",
+    "cluster",
+    "console",
+    "constants",
+    "crypto",
+    "dgram",
+    "diagnostics_channel",
+    "dns",
+    "dns/promises",
+    "domain",
+    "events",
+    "fs",
+    "fs/promises",
+    "http",
+    "http2",
+    "https",
+    "inspector",
+    "inspector/promises",
+    "module",
+    "net",
+    "os",
+    "path",
+    "path/posix",
+    "path/win32",
+    "perf_hooks",
+    "process",
+    "punycode",
+    "querystring",
+    "readline",
+    "readline/promises",
+    "repl",
+    "stream",
+    "stream/consumers",
+    "stream/promises",
+    "stream/web",
+    "string_decoder",
+    "sys",
+    "timers",
+    "timers/promises",
+    "tls",
+    "trace_events",
+    "tty",
+    "url",
+    "util",
+    "util/types",
+    "v8",
+    "vm",
+    "wasi",
+    "worker_threads",
+    "zlib",
+];
+
+/// Whether a bare specifier names a Node.js builtin such as `fs`, which
+/// TypeScript falls back to when no `paths` target exists.
+fn is_node_builtin(specifier: &str) -> bool {
+    NODE_BUILTINS.contains(&specifier)
+}
+
+/// Whether a bare specifier names a package installed in a `node_modules`
+/// above `importer`. TypeScript falls back to those when no `paths` target
+/// exists, e.g. for `react` with a catch-all `*` alias.
+fn is_installed_package(importer: &Utf8Path, specifier: &str) -> bool {
+    let mut segments = specifier.split('/');
+    let name = match (segments.next(), segments.next()) {
+        (Some(scope), Some(name)) if scope.starts_with('@') => format!("{}/{}", scope, name),
+        (Some(name), _) => name.to_string(),
+        _ => return false,
+    };
+    importer
+        .ancestors()
+        .skip(1)
+        .any(|dir| dir.join("node_modules").join(&name).exists())
+}
+
+/// An aliased specifier found in a source file.
+#[derive(Debug)]
+pub struct Alias<'a> {
+    pub specifier: Specifier<'a>,
+    /// The relative specifier that replaces it, or `None` if it does not
+    /// resolve to an emitted file.
+    pub rewrite: Option<String>,
+}
+
+/// Finds every aliased specifier in `source` and resolves it.
+///
+/// Specifiers that are already relative (including the ones para wrote on a
+/// previous run) are never aliases, so running this twice changes nothing.
+pub fn find_aliases<'a>(
+    config: &ParaConfig,
+    importer: &Utf8Path,
+    source: &'a str,
+    logger: &Logger,
+) -> Vec<Alias<'a>> {
+    if config.path_map.is_empty() {
+        return vec![];
+    }
+    scan_specifiers(source)
+        .into_iter()
+        .filter(|specifier| is_aliasable(specifier.value))
+        // reference paths are relative to the file, so one that exists already works
+        .filter(|specifier| {
+            !specifier.reference
+                || !importer
+                    .parent()
+                    .is_some_and(|dir| dir.join(specifier.value).is_file())
+        })
+        .filter_map(|specifier| {
+            let candidates = config.path_map.resolve(specifier.value)?;
+            let rewrite = rewrite_specifier(config, importer, specifier.value, &candidates, logger);
+            // not an alias after all, but a module TypeScript finds without `paths`
+            if rewrite.is_none()
+                && (specifier.reference
+                    || is_node_builtin(specifier.value)
+                    || is_installed_package(importer, specifier.value))
+            {
+                return None;
+            }
+            Some(Alias { specifier, rewrite })
+        })
+        .collect()
+}
+
+/// Rewrites every aliased specifier in `source`, returning the new source, the
+/// number of specifiers that were rewritten and the number of aliases that
+/// could not be resolved. Only the bytes of rewritten specifiers change;
+/// everything else is copied as-is.
+pub fn rewrite_source(
+    config: &ParaConfig,
+    importer: &Utf8Path,
+    source: &str,
+    logger: &Logger,
+) -> (String, usize, usize) {
+    let mut rewritten = String::with_capacity(source.len());
+    let mut rewrites = 0;
+    let mut unresolved = 0;
+    let mut last = 0;
+    for Alias { specifier, rewrite } in find_aliases(config, importer, source, logger) {
+        match rewrite {
+            Some(relative) => {
+                debug::rewrote_specifier(specifier.value, &relative, logger);
+                rewritten.push_str(&source[last..specifier.range.start]);
+                rewritten.push_str(&relative);
+                last = specifier.range.end;
+                rewrites += 1;
+            }
+            None => {
+                warn::unresolved_alias(importer, specifier.value, logger);
+                unresolved += 1;
+            }
+        }
+    }
+    rewritten.push_str(&source[last..]);
+    (rewritten, rewrites, unresolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cli::Level, parser::parse_tsconfig, utils::temp_project as project};
+
+    const TSCONFIG: &str = r#"{
+        "compilerOptions": {
+            "baseUrl": ".",
+            "outDir": "dist",
+            "paths": { "@/*": ["pkg/*", "pkg/other/*"], "$/*": ["node_modules/*"] }
+        }
+    }"#;
+
+    #[test]
+    fn probes_extensions_and_directory_indexes() {
+        let (_dir, root) = project(&[("a.js", ""), ("b/index.mjs", ""), ("c.d/x", "")]);
+        assert_eq!(probe_emitted(&root.join("a")), Some(root.join("a.js")));
+        assert_eq!(probe_emitted(&root.join("a.js")), Some(root.join("a.js")));
+        assert_eq!(
+            probe_emitted(&root.join("b")),
+            Some(root.join("b/index.mjs"))
+        );
+        assert_eq!(probe_emitted(&root.join("c.d")), None);
+        assert_eq!(probe_emitted(&root.join("d")), None);
+    }
+
+    #[test]
+    fn picks_the_first_candidate_that_exists() {
+        let (_dir, root) = project(&[
+            ("tsconfig.json", TSCONFIG),
+            ("pkg/index.ts", ""),
+            ("pkg/lib.ts", ""),
+            ("pkg/other/demo.ts", ""),
+            ("pkg/other/index.ts", ""),
+            ("dist/index.js", ""),
+            ("dist/lib.js", ""),
+            ("dist/other/demo.js", ""),
+            ("dist/other/index.js", ""),
+            ("node_modules/dep/index.js", ""),
+        ]);
+        let config = parse_tsconfig(root.join("tsconfig.json")).unwrap();
+        let logger = Logger(Level::Error);
+        let rewrite = |importer: &str, specifier: &str| {
+            let candidates = config.path_map.resolve(specifier).unwrap();
+            rewrite_specifier(
+                &config,
+                &root.join(importer),
+                specifier,
+                &candidates,
+                &logger,
+            )
+        };
+        assert_eq!(rewrite("dist/index.js", "@/lib").as_deref(), Some("./lib"));
+        assert_eq!(
+            rewrite("dist/index.js", "@/demo").as_deref(),
+            Some("./other/demo")
+        );
+        assert_eq!(
+            rewrite("dist/index.js", "@/other").as_deref(),
+            Some("./other")
+        );
+        assert_eq!(
+            rewrite("dist/other/demo.js", "@/lib").as_deref(),
+            Some("../lib")
+        );
+        assert_eq!(rewrite("dist/index.js", "@/missing"), None);
+        assert_eq!(
+            rewrite("dist/index.js", "$/dep").as_deref(),
+            Some("../node_modules/dep")
+        );
+    }
+
+    #[test]
+    fn maps_explicit_extensions_to_emitted_ones() {
+        let (_dir, root) = project(&[
+            (
+                "tsconfig.json",
+                r#"{ "compilerOptions": { "baseUrl": ".", "rootDir": "src", "outDir": "out",
+                     "paths": { "config": ["src/config/index.ts"] } } }"#,
+            ),
+            ("src/config/index.ts", ""),
+            ("out/config/index.js", ""),
+            ("out/main.js", ""),
+        ]);
+        let config = parse_tsconfig(root.join("tsconfig.json")).unwrap();
+        let candidates = config.path_map.resolve("config").unwrap();
+        let importer = root.join("out/main.js");
+        let rewritten = rewrite_specifier(
+            &config,
+            &importer,
+            "config",
+            &candidates,
+            &Logger(Level::Error),
+        );
+        assert_eq!(rewritten.as_deref(), Some("./config/index.js"));
+    }
+
+    #[test]
+    fn rewrites_declarations_against_declaration_dir() {
+        let (_dir, root) = project(&[
+            (
+                "tsconfig.json",
+                &TSCONFIG.replace(
+                    r#""outDir": "dist","#,
+                    r#""outDir": "dist", "declarationDir": "types","#,
+                ),
+            ),
+            ("pkg/lib.ts", ""),
+            ("pkg/util.ts", ""),
+            ("dist/index.js", ""),
+            ("dist/lib.js", ""),
+            ("dist/util.js", ""),
+            ("types/lib.d.ts", ""),
+            ("node_modules/dep/index.js", ""),
+        ]);
+        let config = parse_tsconfig(root.join("tsconfig.json")).unwrap();
+        let source = concat!(
+            "/// <reference path=\"@/lib.d.ts\" />\n",
+            "export * from \"@/lib\";\n",
+            "export declare const log: typeof import(\"@/lib\").log;\n",
+            "export type U = import(\"@/util\").U;\n",
+            "export type D = import(\"$/dep\").D;\n",
+        );
+        let (rewritten, rewrites, unresolved) = rewrite_source(
+            &config,
+            &root.join("types/index.d.ts"),
+            source,
+            &Logger(Level::Error),
+        );
+        assert_eq!((rewrites, unresolved), (5, 0));
+        assert_eq!(
+            rewritten,
+            concat!(
+                "/// <reference path=\"./lib.d.ts\" />\n",
+                "export * from \"./lib\";\n",
+                "export declare const log: typeof import(\"./lib\").log;\n",
+                // no declaration was emitted, so the JavaScript is all there is
+                "export type U = import(\"../dist/util\").U;\n",
+                "export type D = import(\"../node_modules/dep\").D;\n",
+            )
+        );
+    }
+
+    #[test]
+    fn finds_resolved_and_unresolved_aliases() {
+        let (_dir, root) = project(&[
+            ("tsconfig.json", TSCONFIG),
+            ("pkg/lib.ts", ""),
+            ("dist/lib.js", ""),
+            ("dist/index.js", ""),
+        ]);
+        let config = parse_tsconfig(root.join("tsconfig.json")).unwrap();
+        let source = "import a from \"@/lib\";\nimport b from \"./b\";\nimport c from \"@/c\";\n";
+        let aliases = find_aliases(
+            &config,
+            &root.join("dist/index.js"),
+            source,
+            &Logger(Level::Error),
+        );
+        let found = aliases
+            .iter()
+            .map(|alias| (alias.specifier.value, alias.rewrite.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(found, [("@/lib", Some("./lib")), ("@/c", None)]);
+    }
+
+    #[test]
+    fn preserves_every_byte_outside_specifiers() {
+        // BOM, CRLF and LF line endings, tabs, trailing whitespace and no final newline
+        let source = "\u{feff}import a from \"@/lib\";\r\n\timport b from '@/other' \r\nconst s = \"@/lib\";\n\n// \"@/lib\"\r\nrequire(\"@/lib\")";
+        let golden = "\u{feff}import a from \"./lib\";\r\n\timport b from './other' \r\nconst s = \"@/lib\";\n\n// \"@/lib\"\r\nrequire(\"./lib\")";
+        let (_dir, root) = project(&[
+            ("tsconfig.json", TSCONFIG),
+            ("pkg/lib.ts", ""),
+            ("pkg/other/index.ts", ""),
+            ("dist/lib.js", ""),
+            ("dist/other/index.js", ""),
+            ("dist/index.js", source),
+        ]);
+        let config = parse_tsconfig(root.join("tsconfig.json")).unwrap();
+        let importer = root.join("dist/index.js");
+        let (_, contents) = crate::utils::hash_file(importer.as_std_path()).unwrap();
+        assert_eq!(contents, source.as_bytes());
+
+        let source = std::str::from_utf8(&contents).unwrap();
+        let (rewritten, rewrites, unresolved) =
+            rewrite_source(&config, &importer, source, &Logger(Level::Error));
+        assert_eq!((rewrites, unresolved), (3, 0));
+        assert_eq!(rewritten.as_bytes(), golden.as_bytes());
+    }
+
+    #[test]
+    fn leaves_sources_without_aliases_untouched() {
+        let (_dir, root) = project(&[("tsconfig.json", TSCONFIG), ("dist/index.js", "")]);
+        let config = parse_tsconfig(root.join("tsconfig.json")).unwrap();
+        let source = "import a from \"./a\";\r\n\r\n";
+        let (rewritten, rewrites, unresolved) = rewrite_source(
+            &config,
+            &root.join("dist/index.js"),
+            source,
+            &Logger(Level::Error),
+        );
+        assert_eq!((rewrites, unresolved), (0, 0));
+        assert_eq!(rewritten, source);
+    }
+
+    #[test]
+    fn only_aliasable_specifiers_are_aliases() {
+        assert!(is_aliasable("@/lib"));
+        assert!(is_aliasable("lodash/fp"));
+        assert!(is_aliasable("#internal"));
+        for specifier in [
+            ".",
+            "..",
+            "./lib",
+            "../lib",
+            "/abs/lib",
+            "node:fs",
+            "file:///x",
+        ] {
+            assert!(!is_aliasable(specifier), "{}", specifier);
+        }
+    }
+
+    #[test]
+    fn leaves_relative_absolute_and_package_specifiers_untouched() {
+        let (_dir, root) = project(&[
+            (
+                "tsconfig.json",
+                r#"{ "compilerOptions": { "baseUrl": ".", "outDir": "dist",
+                     "paths": { "*": ["types/*"] } } }"#,
+            ),
+            ("types/lib.d.ts", ""),
+            ("dist/lib.js", ""),
+            ("dist/index.js", ""),
+            ("node_modules/@scope/pkg/index.js", ""),
+            ("node_modules/react/index.js", ""),
+        ]);
+        let config = parse_tsconfig(root.join("tsconfig.json")).unwrap();
+        let source = r#"
+            import a from "./lib";
+            import b from "../dist/lib";
+            import c from "/abs/lib";
+            import d from "node:fs";
+            import e from "react";
+            import f from "@scope/pkg/sub";
+            import g from "missing";
+        "#;
+        let aliases = find_aliases(
+            &config,
+            &root.join("dist/index.js"),
+            source,
+            &Logger(Level::Error),
+        );
+        let found = aliases
+            .iter()
+            .map(|alias| alias.specifier.value)
+            .collect::<Vec<_>>();
+        assert_eq!(found, ["missing"]);
+    }
+
+    #[test]
+    fn node_builtins_are_not_aliases() {
+        let (_dir, root) = project(&[
+            (
+                "tsconfig.json",
+                r#"{ "compilerOptions": { "baseUrl": ".", "rootDir": "src", "outDir": "dist",
+                     "paths": { "*": ["src/types/*"] } } }"#,
+            ),
+            ("src/a.ts", ""),
+            ("dist/a.js", ""),
+        ]);
+        let config = parse_tsconfig(root.join("tsconfig.json")).unwrap();
+        let source = "const fs = require(\"fs\");\nimport { join } from \"path/posix\";\n";
+        let importer = root.join("dist/a.js");
+        let logger = Logger(Level::Error);
+        assert!(find_aliases(&config, &importer, source, &logger).is_empty());
+        assert_eq!(
+            rewrite_source(&config, &importer, source, &logger),
+            (source.to_string(), 0, 0)
+        );
+    }
+
+    #[test]
+    fn reference_paths_are_relative_to_the_file() {
+        let (_dir, root) = project(&[
+            (
+                "tsconfig.json",
+                r#"{ "compilerOptions": { "baseUrl": ".", "rootDir": "src", "outDir": "dist",
+                     "paths": { "*": ["src/types/*"] } } }"#,
+            ),
+            ("src/index.ts", ""),
+            ("src/types/globals.d.ts", ""),
+            ("dist/index.d.ts", ""),
+            ("dist/globals.d.ts", ""),
+        ]);
+        let config = parse_tsconfig(root.join("tsconfig.json")).unwrap();
+        let source = concat!(
+            "/// <reference path=\"globals.d.ts\" />\n",
+            "/// <reference path=\"missing.d.ts\" />\n",
+        );
+        let importer = root.join("dist/index.d.ts");
+        let logger = Logger(Level::Error);
+        assert!(find_aliases(&config, &importer, source, &logger).is_empty());
+        assert_eq!(
+            rewrite_source(&config, &importer, source, &logger),
+            (source.to_string(), 0, 0)
+        );
+    }
+}
@@ -0,0 +1,480 @@
+//! A small JavaScript tokenizer that finds module specifiers.
+//!
+//! This is not a parser. It understands just enough of the language (strings,
+//! template literals, comments and regular expression literals) to know when a
+//! string literal is really a module specifier, so that aliases mentioned in
+//! comments or plain string data are never touched. The only comments it
+//! reads are `/// <reference path="..." />` directives of declaration files.
+
+use std::ops::Range;
+
+/// A module specifier found in a source file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Specifier<'a> {
+    /// The specifier as written, without quotes.
+    pub value: &'a str,
+    /// Byte range of `value` in the scanned source.
+    pub range: Range<usize>,
+    /// Whether this is the path of a `/// <reference path="..." />` directive,
+    /// which is relative to the file rather than a module specifier.
+    pub reference: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token<'a> {
+    Ident(&'a str),
+    Punct(u8),
+    /// A string literal, or a template literal without substitutions.
+    Str(Range<usize>),
+    /// Numbers, regular expressions and template literals with substitutions.
+    Value,
+}
+
+impl Token<'_> {
+    fn is_ident(&self, ident: &str) -> bool {
+        matches!(self, Token::Ident(i) if *i == ident)
+    }
+    fn is_punct(&self, punct: u8) -> bool {
+        matches!(self, Token::Punct(p) if *p == punct)
+    }
+}
+
+/// Keywords after which a `/` starts a regular expression instead of a division.
+const REGEX_PRECEDING_KEYWORDS: &[&str] = &[
+    "return",
+    "typeof",
+    "instanceof",
+    "in",
+    "of",
+    "new",
+    "delete",
+    "void",
+    "throw",
+    "case",
+    "do",
+    "else",
+    "yield",
+    "await",
+];
+
+struct Lexer<'a> {
+    src: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+    /// `true` for each open `${`, `false` for each open `{`.
+    braces: Vec<bool>,
+    /// Whether the previous token could end an expression.
+    expr_end: bool,
+    /// Byte ranges of the paths of `/// <reference path="..." />` directives.
+    references: Vec<Range<usize>>,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(src: &'a str) -> Self {
+        Self {
+            src,
+            bytes: src.as_bytes(),
+            pos: 0,
+            braces: vec![],
+            expr_end: false,
+            references: vec![],
+        }
+    }
+
+    fn peek(&self, offset: usize) -> Option<u8> {
+        self.bytes.get(self.pos + offset).copied()
+    }
+
+    fn skip_trivia(&mut self) {
+        // hashbang
+        if self.pos == 0 && self.src.starts_with("#!") {
+            self.skip_line();
+        }
+        while let Some(b) = self.peek(0) {
+            match b {
+                b' ' | b'\t' | b'\n' | b'\r' | 0x0b | 0x0c => self.pos += 1,
+                b'/' if self.peek(1) == Some(b'/') => {
+                    let start = self.pos;
+                    self.skip_line();
+                    if let Some(path) = reference_path(&self.src[start..self.pos]) {
+                        self.references.push(start + path.start..start + path.end);
+                    }
+                }
+                b'/' if self.peek(1) == Some(b'*') => {
+                    self.pos = match self.src[self.pos + 2..].find("*/") {
+                        Some(end) => self.pos + 2 + end + 2,
+                        None => self.bytes.len(),
+                    };
+                }
+                // byte order mark, non-breaking and other unicode spaces
+                _ if !b.is_ascii()
+                    && self.src[self.pos..]
+                        .starts_with(|c: char| c == '\u{feff}' || c.is_whitespace()) =>
+                {
+                    self.pos += self.src[self.pos..].chars().next().unwrap().len_utf8();
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn skip_line(&mut self) {
+        self.pos = match self.src[self.pos..].find('\n') {
+            Some(end) => self.pos + end,
+            None => self.bytes.len(),
+        };
+    }
+
+    /// Scans a quoted string starting at the opening quote. Strings that are
+    /// not closed before the end of the line yield `None`.
+    fn string(&mut self, quote: u8) -> Option<Range<usize>> {
+        let start = self.pos + 1;
+        self.pos = start;
+        while let Some(b) = self.peek(0) {
+            match b {
+                b'\\' => self.pos += 2,
+                b'\n' => return None,
+                _ if b == quote => {
+                    self.pos += 1;
+                    return Some(start..self.pos - 1);
+                }
+                _ => self.pos += 1,
+            }
+        }
+        self.pos = self.bytes.len();
+        None
+    }
+
+    /// Scans a template literal (or the continuation of one after a `}`) up
+    /// to its closing backtick or the next `${`. Only a template without any
+    /// substitutions is a plain string.
+    fn template(&mut self, head: bool) -> Token<'a> {
+        let start = self.pos;
+        while let Some(b) = self.peek(0) {
+            match b {
+                b'\\' => self.pos += 2,
+                b'`' => {
+                    self.pos += 1;
+                    return match head {
+                        true => Token::Str(start..self.pos - 1),
+                        false => Token::Value,
+                    };
+                }
+                b'$' if self.peek(1) == Some(b'{') => {
+                    self.pos += 2;
+                    self.braces.push(true);
+                    return Token::Value;
+                }
+                _ => self.pos += 1,
+            }
+        }
+        self.pos = self.bytes.len();
+        Token::Value
+    }
+
+    fn regex(&mut self) {
+        let mut in_class = false;
+        self.pos += 1;
+        while let Some(b) = self.peek(0) {
+            self.pos += 1;
+            match b {
+                b'\\' => self.pos += 1,
+                b'[' => in_class = true,
+                b']' => in_class = false,
+                b'/' if !in_class => break,
+                b'\n' => return,
+                _ => (),
+            }
+        }
+        while self.peek(0).is_some_and(is_ident_byte) {
+            self.pos += 1;
+        }
+    }
+
+    fn next_token(&mut self) -> Option<Token<'a>> {
+        self.skip_trivia();
+        let b = self.peek(0)?;
+        let token = match b {
+            b'"' | b'\'' => match self.string(b) {
+                Some(range) => Token::Str(range),
+                None => Token::Value,
+            },
+            b'`' => {
+                self.pos += 1;
+                self.template(true)
+            }
+            b'0'..=b'9' => {
+                while self.peek(0).is_some_and(|b| is_ident_byte(b) || b == b'.') {
+                    self.pos += 1;
+                }
+                Token::Value
+            }
+            b'.' if self.peek(1).is_some_and(|b| b.is_ascii_digit()) => {
+                self.pos += 1;
+                while self.peek(0).is_some_and(|b| is_ident_byte(b) || b == b'.') {
+                    self.pos += 1;
+                }
+                Token::Value
+            }
+            b'/' if !self.expr_end => {
+                self.regex();
+                Token::Value
+            }
+            b'{' => {
+                self.pos += 1;
+                self.braces.push(false);
+                Token::Punct(b)
+            }
+            b'}' => {
+                self.pos += 1;
+                match self.braces.pop() {
+                    Some(true) => self.template(false),
+                    _ => Token::Punct(b),
+                }
+            }
+            _ if is_ident_byte(b) || b == b'#' => {
+                let start = self.pos;
+                self.pos += 1;
+                while self.peek(0).is_some_and(is_ident_byte) {
+                    self.pos += 1;
+                }
+                Token::Ident(&self.src[start..self.pos])
+            }
+            _ => {
+                self.pos += 1;
+                Token::Punct(b)
+            }
+        };
+        self.pos = self.pos.min(self.bytes.len());
+        self.expr_end = match &token {
+            Token::Ident(ident) => !REGEX_PRECEDING_KEYWORDS.contains(ident),
+            Token::Punct(p) => matches!(p, b')' | b']'),
+            Token::Str(_) | Token::Value => true,
+        };
+        Some(token)
+    }
+}
+
+/// Whether the tokens before a string literal make it the first argument of
+/// `require(`, `import(`, `require.resolve(` or `import.meta.resolve(`.
+fn is_specifier_call(prev: &[Token]) -> bool {
+    use Token::{Ident, Punct};
+    let not_member = |rest: &[Token]| !rest.last().is_some_and(|t| t.is_punct(b'.'));
+    match prev {
+        [.., Ident("import"), Punct(b'.'), Ident("meta"), Punct(b'.'), Ident("resolve"), Punct(b'(')] => {
+            true
+        }
+        [rest @ .., Ident("require"), Punct(b'.'), Ident("resolve"), Punct(b'(')] => {
+            not_member(rest)
+        }
+        [rest @ .., Ident("require" | "import"), Punct(b'(')] => not_member(rest),
+        _ => false,
+    }
+}
+
+/// Finds the value of the `path` attribute of a triple-slash reference
+/// directive, as a byte range in `comment`.
+fn reference_path(comment: &str) -> Option<Range<usize>> {
+    let rest = comment.strip_prefix("///")?.trim_start();
+    let mut rest = rest
+        .strip_prefix("<reference")
+        .filter(|rest| rest.starts_with(char::is_whitespace))?;
+    // `name="value"` attributes, in any order
+    loop {
+        rest = rest.trim_start();
+        let name_end = rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '-')?;
+        let (name, after) = rest.split_at(name_end);
+        if name.is_empty() {
+            return None;
+        }
+        let after = after.trim_start().strip_prefix('=')?.trim_start();
+        let quote = after.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+        let value = &after[1..];
+        let end = value.find(quote)?;
+        if name == "path" {
+            let start = comment.len() - value.len();
+            return Some(start..start + end);
+        }
+        rest = &value[end + 1..];
+    }
+}
+
+/// Identifier bytes. Any non-ASCII byte is treated as part of an identifier,
+/// which is good enough to step over unicode names.
+fn is_ident_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b == b'$' || !b.is_ascii()
+}
+
+/// Finds every module specifier in a JavaScript or TypeScript source.
+///
+/// Recognized forms:
+///
+/// ```text
+/// import x from "x"          export * from "x"
+/// import type { X } from "x" export { a } from "x"
+/// import "x"                 import("x") / import(`x`)
+/// require("x")               require.resolve("x")
+/// import.meta.resolve("x")  type T = import("x").T
+/// /// <reference path="x" />
+/// ```
+pub fn scan_specifiers(src: &str) -> Vec<Specifier<'_>> {
+    let mut lexer = Lexer::new(src);
+    let mut specifiers = vec![];
+    // The most recent tokens, newest last. Six is enough for `import.meta.resolve(`.
+    let mut prev: Vec<Token> = vec![];
+    // A call argument waiting for a `)` or `,` to confirm it.
+    let mut pending: Option<Range<usize>> = None;
+    // Whether we are inside an `import`/`export` declaration.
+    let mut in_declaration = false;
+
+    while let Some(token) = lexer.next_token() {
+        if let Some(range) = pending.take() {
+            if token.is_punct(b')') || token.is_punct(b',') {
+                specifiers.push(Specifier {
+                    value: &src[range.clone()],
+                    range,
+                    reference: false,
+                });
+            }
+        }
+
+        let is_member = prev.last().is_some_and(|t| t.is_punct(b'.'));
+        let follows = |ident: &str| prev.last().is_some_and(|t| t.is_ident(ident));
+
+        match &token {
+            Token::Str(range) => {
+                if is_specifier_call(&prev) {
+                    pending = Some(range.clone());
+                } else if in_declaration
+                    && src.as_bytes()[range.start - 1] != b'`'
+                    && (follows("from") || follows("import"))
+                {
+                    specifiers.push(Specifier {
+                        value: &src[range.clone()],
+                        range: range.clone(),
+                        reference: false,
+                    });
+                    in_declaration = false;
+                }
+            }
+            Token::Ident(ident @ ("import" | "export")) if !is_member => {
+                // `import(` and `import.meta` are expressions, not declarations
+                in_declaration = *ident == "export"
+                    || !lexer.src[lexer.pos..].trim_start().starts_with(['(', '.']);
+            }
+            Token::Punct(b';') => in_declaration = false,
+            _ => (),
+        }
+
+        prev.push(token);
+        if prev.len() > 6 {
+            prev.remove(0);
+        }
+    }
+
+    specifiers.extend(lexer.references.into_iter().map(|range| Specifier {
+        value: &src[range.clone()],
+        range,
+        reference: true,
+    }));
+    specifiers.sort_by_key(|specifier| specifier.range.start);
+    specifiers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(src: &str) -> Vec<&str> {
+        scan_specifiers(src).into_iter().map(|s| s.value).collect()
+    }
+
+    #[test]
+    fn finds_static_and_side_effect_imports() {
+        let src = r#"
+            import x from "a";
+            import * as y from 'b';
+            import { c, d as e } from "c";
+            import type { T } from "d";
+            import "e";
+            export * from "f";
+            export * as ns from "g";
+            export { h } from 'h';
+            export { default } from "i"
+        "#;
+        assert_eq!(values(src), ["a", "b", "c", "d", "e", "f", "g", "h", "i"]);
+    }
+
+    #[test]
+    fn finds_calls() {
+        let src = r#"
+            const a = require("a");
+            const b = await import('b');
+            const c = import(`c`);
+            const d = require.resolve("d");
+            const e = import.meta.resolve("e");
+            const f = import("f", { with: { type: "json" } });
+        "#;
+        assert_eq!(values(src), ["a", "b", "c", "d", "e", "f"]);
+    }
+
+    #[test]
+    fn ignores_strings_comments_and_non_specifiers() {
+        let src = r#"
+            // require("a")
+            /* import x from "b" */
+            const s = 'require("c")';
+            const t = `import("d")`;
+            const u = import(`e${x}`);
+            const v = require("f" + g);
+            const w = foo.require("h");
+            const from = "i";
+            export const j = "j";
+            obj.import("k");
+        "#;
+        assert!(values(src).is_empty(), "{:?}", values(src));
+    }
+
+    #[test]
+    fn steps_over_regular_expressions() {
+        let src = r#"
+            const re = /"|require\("x"\)/g;
+            const n = 4 / 2; const m = a / "b".length;
+            const r = require("y");
+        "#;
+        assert_eq!(values(src), ["y"]);
+    }
+
+    #[test]
+    fn steps_over_nested_template_literals() {
+        let src = "const t = `${ `${ '}' }` } require(\"a\")`; require(\"b\");";
+        assert_eq!(values(src), ["b"]);
+    }
+
+    #[test]
+    fn reports_byte_ranges_inside_quotes() {
+        let src = "\u{feff}import x from \"@/lib\";";
+        let specifiers = scan_specifiers(src);
+        assert_eq!(specifiers.len(), 1);
+        assert_eq!(&src[specifiers[0].range.clone()], "@/lib");
+    }
+
+    #[test]
+    fn finds_declaration_file_specifiers() {
+        let src = r#"
+            /// <reference path="@/globals.d.ts" />
+            /// <reference types="node" />
+            ///<reference no-default-lib="true" path='./local.d.ts'/>
+            //// <reference path="not-a-directive" />
+            // <reference path="neither" />
+            import type { A } from "a";
+            export declare const b: import("b").B<Array<string>>;
+            export declare function c(x: typeof import("c")): import('d').D[];
+            declare const e: Promise<import(`e`).E>;
+        "#;
+        assert_eq!(
+            values(src),
+            ["@/globals.d.ts", "./local.d.ts", "a", "b", "c", "d", "e"]
+        );
+    }
+}
@@ -7,9 +7,9 @@ use globset::GlobSet;
 pub enum Action {
     ReadFile(PathBuf),
     ReadDir(PathBuf),
-    CompareHash(PathBuf, String, String),
-    FindCaptures(String, String),
-    CacheFile(String),
+    CompareHash(PathBuf, String, Vec<u8>),
+    FindCaptures(PathBuf, String, Vec<u8>),
+    CacheFile(PathBuf, String),
     FinishJob(PathBuf, String),
 }
 
@@ -22,9 +22,9 @@ impl RevealPath for Action {
         match self {
             Action::ReadFile(path) => Some(path),
             Action::ReadDir(path) => Some(path),
-            Action::CacheFile(_) => None,
+            Action::CacheFile(path, _) => Some(path),
             Action::CompareHash(path, _, _) => Some(path),
-            Action::FindCaptures(_, _) => None,
+            Action::FindCaptures(path, _, _) => Some(path),
             Action::FinishJob(path, _) => Some(path),
         }
     }
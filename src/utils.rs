@@ -1,4 +1,5 @@
 use camino::{Utf8Path, Utf8PathBuf};
+use clean_path::clean;
 use sha2::{
     digest::{
         generic_array::GenericArray,
@@ -8,7 +9,7 @@ use sha2::{
 };
 use std::{
     fs::File,
-    io::{BufRead, BufReader},
+    io::{BufRead, BufReader, Write},
     ops::{Deref, DerefMut},
     path::{Path, PathBuf},
 };
@@ -17,19 +18,119 @@ use std::{
 pub type FileHash =
     GenericArray<u8, UInt<UInt<UInt<UInt<UInt<UInt<UTerm, B1>, B0>, B0>, B0>, B0>, B0>>;
 
-/// Hashes the contents of a file and returns a tuple of the hash and string contents of the file.
-pub fn hash_file(node: &Path) -> (String, String) {
-    let file = File::open(node).unwrap();
-    let buff = BufReader::new(file);
+/// Hashes the contents of a file and returns a tuple of the hash and the raw bytes of the file.
+///
+/// The bytes are returned untouched (byte order mark, line endings and all) so
+/// that writing back a rewritten file only changes the rewritten specifiers.
+/// They are hashed as they are read, so large files are only walked once.
+pub fn hash_file(node: &Path) -> std::io::Result<(String, Vec<u8>)> {
+    let mut reader = BufReader::new(File::open(node)?);
+    let mut contents = vec![];
     let mut hasher = Sha256::new();
-    let mut contents = String::new();
-    for line in buff.lines() {
-        let line = line.unwrap();
-        contents.push_str(&line);
-        hasher.update(line);
+    loop {
+        let chunk = match reader.fill_buf() {
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            chunk => chunk?,
+        };
+        if chunk.is_empty() {
+            break;
+        }
+        hasher.update(chunk);
+        contents.extend_from_slice(chunk);
+        let len = chunk.len();
+        reader.consume(len);
     }
     let hash: FileHash = hasher.finalize();
-    (format!("{:x}", hash), contents)
+    Ok((format!("{:x}", hash), contents))
+}
+
+/// Whether the contents of a file look binary, i.e. hold a NUL byte within
+/// the first 8000 bytes (the same heuristic `git` uses). Source text never does.
+pub fn is_binary(contents: &[u8]) -> bool {
+    contents[..contents.len().min(8000)].contains(&0)
+}
+
+/// Hashes bytes the same way [`hash_file`] hashes a file, e.g. to remember the
+/// hash of a file that was just written.
+pub fn hash_bytes(bytes: &[u8]) -> String {
+    let hash: FileHash = Sha256::digest(bytes);
+    format!("{:x}", hash)
+}
+
+/// Replaces the contents of a file without ever leaving it half-written.
+///
+/// The bytes go to a temporary sibling first, which takes the permissions of
+/// the original, is synced to disk and is then renamed over the original. The
+/// file gets a fresh mtime like any other write, so watchers notice the change.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(".{}.{}.para-tmp", file_name, std::process::id()));
+    let write = || {
+        let mut file = File::options().write(true).create_new(true).open(&temp)?;
+        file.write_all(bytes)?;
+        if let Ok(metadata) = std::fs::metadata(path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.sync_all()?;
+        std::fs::rename(&temp, path)
+    };
+    write().inspect_err(|_| {
+        std::fs::remove_file(&temp).ok();
+    })
+}
+
+/// Computes the path of `to` relative to the directory `from`.
+///
+/// Both paths are made absolute against the current working directory first,
+/// so mixing relative and absolute paths is fine.
+pub fn relative_path(from: &Utf8Path, to: &Utf8Path) -> Utf8PathBuf {
+    let absolute = |path: &Utf8Path| {
+        let path = match path.is_absolute() {
+            true => path.to_path_buf(),
+            false => Cwd::new().join(path),
+        };
+        Utf8PathBuf::from_path_buf(clean(path)).unwrap()
+    };
+    let (from, to) = (absolute(from), absolute(to));
+    let common = from
+        .components()
+        .zip(to.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut relative = Utf8PathBuf::new();
+    for _ in from.components().skip(common) {
+        relative.push("..");
+    }
+    for component in to.components().skip(common) {
+        relative.push(component);
+    }
+    relative
+}
+
+/// Formats a relative path as an ECMAScript module specifier, always starting
+/// with `./` or `../` and always using forward slashes.
+pub fn to_relative_specifier(path: &Utf8Path) -> String {
+    let specifier = path
+        .components()
+        .map(|c| c.as_str())
+        .collect::<Vec<_>>()
+        .join("/");
+    match specifier.as_str() {
+        "" => ".".to_string(),
+        s if s.starts_with("../") || s == ".." => specifier,
+        _ => format!("./{}", specifier),
+    }
+}
+
+/// Converts a byte offset into a 1-based line and column, counting columns in
+/// characters.
+pub fn line_col(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
 }
 
 // Create all directories up to the file, then create the file if it doesn't exist.
@@ -44,6 +145,20 @@ pub fn create_all_dir_until_file(dir: &Path, path: &PathBuf) -> std::io::Result<
     }
 }
 
+/// Writes a project with the given files into a temporary directory, returning
+/// the directory guard and its path.
+#[cfg(test)]
+pub fn temp_project(files: &[(&str, &str)]) -> (tempfile::TempDir, Utf8PathBuf) {
+    let dir = tempfile::TempDir::new().unwrap();
+    let root = Utf8PathBuf::from_path_buf(dir.path().to_path_buf()).unwrap();
+    for (path, contents) in files {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+    (dir, root)
+}
+
 pub struct Cwd(Utf8PathBuf);
 
 impl Cwd {
@@ -90,3 +205,79 @@ impl AsRef<Path> for Cwd {
         self.0.as_ref()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_path_between_siblings_and_parents() {
+        let cwd = Cwd::new();
+        let dist = cwd.join("dist");
+        assert_eq!(relative_path(&dist, &dist.join("lib")), "lib");
+        assert_eq!(
+            relative_path(&dist.join("other"), &dist.join("lib")),
+            Utf8PathBuf::from("../lib")
+        );
+        assert_eq!(relative_path(Utf8Path::new("dist/a/b"), &dist), "../..");
+    }
+
+    #[test]
+    fn relative_specifiers_always_start_with_a_dot() {
+        assert_eq!(to_relative_specifier(Utf8Path::new("lib")), "./lib");
+        assert_eq!(to_relative_specifier(Utf8Path::new("../lib")), "../lib");
+        assert_eq!(to_relative_specifier(Utf8Path::new("")), ".");
+        assert_eq!(to_relative_specifier(Utf8Path::new("a/b.js")), "./a/b.js");
+    }
+
+    #[test]
+    fn line_col_is_one_based() {
+        let source = "ab\r\nc\u{e9}d\n";
+        assert_eq!(line_col(source, 0), (1, 1));
+        assert_eq!(line_col(source, 1), (1, 2));
+        assert_eq!(line_col(source, 4), (2, 1));
+        assert_eq!(line_col(source, 7), (2, 3));
+    }
+
+    #[test]
+    fn hashes_every_byte() {
+        let (_dir, root) = temp_project(&[("a", "a\nb"), ("b", "ab\n"), ("c", "a\r\nb")]);
+        let hash = |name: &str| hash_file(root.join(name).as_std_path()).unwrap();
+        let (a, contents) = hash("a");
+        assert_eq!(contents, b"a\nb");
+        assert_ne!(a, hash("b").0);
+        assert_ne!(a, hash("c").0);
+        assert_eq!(a, hash_bytes(b"a\nb"));
+        assert!(hash_file(root.join("missing").as_std_path()).is_err());
+    }
+
+    #[test]
+    fn writes_atomically_and_keeps_permissions() {
+        let (_dir, root) = temp_project(&[("dist/a.js", "before")]);
+        let path = root.join("dist/a.js");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o754)).unwrap();
+        }
+        write_atomic(path.as_std_path(), b"after").unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"after");
+        assert_eq!(root.join("dist").read_dir().unwrap().count(), 1);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o754);
+        }
+    }
+
+    #[test]
+    fn failed_atomic_writes_leave_the_original() {
+        let (_dir, root) = temp_project(&[("dist/a.js", "before")]);
+        // renaming a file over a directory fails
+        let path = root.join("dist");
+        assert!(write_atomic(path.as_std_path(), b"after").is_err());
+        assert_eq!(std::fs::read(root.join("dist/a.js")).unwrap(), b"before");
+        assert_eq!(root.read_dir().unwrap().count(), 1);
+    }
+}